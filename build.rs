@@ -0,0 +1,54 @@
+//! 构建脚本：收集构建元数据，供`toolboxer --version`显示
+//!
+//! 通过`cargo:rustc-env`导出以下环境变量，在编译期由`env!`读取：
+//! - `TOOLBOXER_GIT_HASH` - 当前git提交的短哈希（不可用时为`unknown`）
+//! - `TOOLBOXER_BUILD_DATE` - 构建日期（UTC，`YYYY-MM-DD`）
+//! - `TOOLBOXER_TARGET` - 目标平台三元组
+
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    let git_hash = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|hash| !hash.is_empty())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let target = std::env::var("TARGET").unwrap_or_else(|_| "unknown".to_string());
+
+    println!("cargo:rustc-env=TOOLBOXER_GIT_HASH={}", git_hash);
+    println!("cargo:rustc-env=TOOLBOXER_BUILD_DATE={}", build_date());
+    println!("cargo:rustc-env=TOOLBOXER_TARGET={}", target);
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+}
+
+/// 返回当前UTC日期，格式为`YYYY-MM-DD`
+fn build_date() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86_400)
+        .unwrap_or(0) as i64;
+
+    // 由1970-01-01起的天数换算公历日期
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
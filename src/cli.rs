@@ -3,38 +3,130 @@
 //! 本模块定义Toolboxer应用程序的命令行参数结构
 //! 以及各个子命令的配置项。
 
-use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use crate::config::{ConfigOverrides, OutputFormat, SortBy};
+use crate::error::{Error, Result};
+use crate::utils::{parse_size, parse_time_spec};
+use clap::{ArgAction, Parser, Subcommand, ValueEnum};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// `--version`显示的完整版本信息，包含构建元数据（由build.rs生成）
+pub const LONG_VERSION: &str = concat!(
+    env!("CARGO_PKG_VERSION"),
+    "\ncommit: ",
+    env!("TOOLBOXER_GIT_HASH"),
+    "\nbuild date: ",
+    env!("TOOLBOXER_BUILD_DATE"),
+    "\ntarget: ",
+    env!("TOOLBOXER_TARGET"),
+);
 
 /// 主命令行接口结构
 #[derive(Parser)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, long_version = LONG_VERSION, about, long_about = None)]
 pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
+
+    /// 错误输出格式
+    #[arg(long, global = true, value_enum, default_value_t = ErrorFormat::Human)]
+    pub error_format: ErrorFormat,
+
+    /// 在stderr输出诊断信息（-vv额外输出每个被过滤条目的原因）
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// 仅输出核心数据，不输出警告、统计和汇总信息
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// 何时使用彩色输出
+    #[arg(long, global = true, value_enum, value_name = "WHEN", default_value_t = ColorWhen::Auto)]
+    pub color: ColorWhen,
+}
+
+/// 彩色输出的启用条件
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum ColorWhen {
+    /// 标准输出为终端时启用
+    Auto,
+    /// 始终启用（包括输出被重定向时）
+    Always,
+    /// 始终禁用
+    Never,
+}
+
+impl ColorWhen {
+    /// 判断是否应输出颜色，`Auto`时检测标准输出是否为终端
+    pub fn enabled(self) -> bool {
+        self.enabled_for(std::io::stdout().is_terminal())
+    }
+
+    /// 按给定的终端检测结果判断是否应输出颜色
+    ///
+    /// # 参数
+    /// * `is_terminal` - 输出目标是否为终端，只影响`Auto`
+    pub fn enabled_for(self, is_terminal: bool) -> bool {
+        match self {
+            ColorWhen::Auto => is_terminal,
+            ColorWhen::Always => true,
+            ColorWhen::Never => false,
+        }
+    }
+}
+
+/// 错误信息的输出格式
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum ErrorFormat {
+    /// 人类可读的文本
+    Human,
+    /// 单行JSON，便于其他工具解析
+    Json,
+}
+
+/// portown的输出格式
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum PortownFormat {
+    /// 连接表格
+    Table,
+    /// 仅输出统计数字，每行一个`key=value`
+    Count,
+    /// 连接列表的JSON数组
+    Json,
 }
 
 /// 枚举表示可用的子命令
 #[derive(Subcommand)]
 pub enum Commands {
     /// 以树状结构显示目录
-    Tree(TreeArgs),
+    Tree(Box<TreeArgs>),
     /// 显示端口占用信息
     Portown(PortownArgs),
+    /// 检查运行环境并输出诊断报告
+    Doctor,
 }
 // Additional subcommands will be added here as the toolkit expands
 
 /// 'tree'子命令的参数
-#[derive(Parser)]
+#[derive(Parser, Clone)]
 pub struct TreeArgs {
-    /// 生成目录树的根路径
-    #[arg(default_value = ".")]
-    pub path: PathBuf,
+    /// 生成目录树的根路径（未指定时使用配置文件中的default_path，均未设置时为当前目录）
+    pub path: Option<PathBuf>,
 
     /// 最大显示深度
     #[arg(short = 'd', long)]
     pub max_depth: Option<usize>,
 
+    /// 仅以平铺列表输出恰好位于第N层的条目（相对路径），0为根目录本身
+    #[arg(long, value_name = "N", conflicts_with = "max_depth")]
+    pub only_depth: Option<usize>,
+
+    /// 仅输出名称匹配模式（支持`*`、`?`）的目录的完整路径，每行一个；
+    /// 默认搜索整个子树，可用--max-depth限制
+    #[arg(long, value_name = "PATTERN", conflicts_with = "only_depth")]
+    pub find_dirs: Option<String>,
+
     /// 包含隐藏文件
     #[arg(short, long)]
     pub all: bool,
@@ -47,10 +139,22 @@ pub struct TreeArgs {
     #[arg(short = 's', long)]
     pub size: bool,
 
+    /// 大小使用1000进制的SI单位（kB、MB），默认为1024进制（KiB、MiB）
+    #[arg(long)]
+    pub si: bool,
+
+    /// 将文件大小作为右对齐的固定宽度列显示在目录树之前（类似`ls -l`），目录留空
+    #[arg(long, requires = "size")]
+    pub align_sizes: bool,
+
     /// 显示最后修改时间
     #[arg(short = 'M', long = "mod")]
     pub modified: bool,
 
+    /// 配合--mod，30天内的修改时间显示为相对时间（如`3 hours ago`），更早的保持原格式
+    #[arg(long, requires = "modified")]
+    pub relative_dates: bool,
+
     /// 按类型排序
     #[arg(short = 'T', long = "type")]
     pub type_sort: bool,
@@ -63,16 +167,239 @@ pub struct TreeArgs {
     #[arg(short = 't', long = "date")]
     pub date_sort: bool,
 
+    /// 不排序，按文件系统返回的顺序输出（最快，等同于--sort none）
+    #[arg(long)]
+    pub no_sort: bool,
+
+    /// 排序方式：name（默认，按名称）、type、size、date或none（文件系统顺序）
+    #[arg(long, value_enum, value_name = "KEY")]
+    pub sort: Option<SortBy>,
+
+    /// 倒序排列（如`--reverse`为名称倒序，`-S --reverse`为从大到小）
+    #[arg(short, long)]
+    pub reverse: bool,
+
+    /// 目录排在文件之前，两组内部保持所选的排序方式
+    #[arg(long)]
+    pub dirs_first: bool,
+
+    /// 在目录树之前输出规范化后的绝对根路径作为标题（仅对默认的树形格式生效）
+    #[arg(long)]
+    pub print_root: bool,
+
     /// 按模式过滤文件
     #[arg(short, long)]
     pub filter: Option<String>,
     /// 仅显示目录结构
     #[arg(short = 'D', long = "dirs")]
     pub directories_only: bool,
+
+    /// 从标准输入读取路径列表（每行一个），逐个作为根目录显示
+    #[arg(long)]
+    pub stdin: bool,
+
+    /// 与另一个目录比较，标注新增(+)、删除(-)和大小变化(~)的条目
+    #[arg(long, value_name = "OTHER")]
+    pub diff: Option<PathBuf>,
+
+    /// 标注git状态（M、A、??等），根目录不在git仓库中时忽略
+    #[arg(long)]
+    pub git: bool,
+
+    /// 最多输出的条目数，超出后截断（默认不限制）
+    #[arg(long, value_name = "N")]
+    pub max_files: Option<usize>,
+
+    /// 在名称后追加类型标识：目录`/`、可执行文件`*`、符号链接`@`
+    #[arg(short = 'F', long)]
+    pub classify: bool,
+
+    /// 显示相对于根目录的完整路径而非文件名
+    #[arg(long)]
+    pub full_path: bool,
+
+    /// 显示绝对路径（隐含--full-path）
+    #[arg(long)]
+    pub absolute: bool,
+
+    /// 显示相对于指定目录的路径（无法计算相对路径时显示绝对路径）
+    #[arg(long, value_name = "DIR", conflicts_with = "absolute")]
+    pub relative_to: Option<PathBuf>,
+
+    /// 根路径为符号链接时不跟随，只显示链接本身（默认跟随根路径，但不跟随其中的链接）
+    #[arg(long)]
+    pub no_deref_args: bool,
+
+    /// 仅输出统计数字：directories=<d> files=<f> bytes=<b>
+    #[arg(long)]
+    pub count_only: bool,
+
+    /// 按扩展名统计文件数和总大小，按文件数降序输出表格，不显示目录树
+    /// （无扩展名的文件归入`(none)`）
+    #[arg(long, conflicts_with = "count_only")]
+    pub stats_by_ext: bool,
+
+    /// 统计大小时同一文件的多个硬链接只计一次（与`du`相同，仅Unix有效）
+    #[arg(long)]
+    pub count_links_once: bool,
+
+    /// 仅显示不小于该大小的文件（如1K、10M）
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub min_size: Option<u64>,
+
+    /// 仅显示不大于该大小的文件（如1K、10M）
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub max_size: Option<u64>,
+
+    /// 超过该大小（如1G）的文件不计算摘要（--manifest中标记为跳过），也不计入大小统计
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub skip_larger_than: Option<u64>,
+
+    /// 仅显示在此之后修改的文件（日期如2024-01-01，或时长如7d、24h）
+    #[arg(long, value_name = "TIME", value_parser = parse_time_spec)]
+    pub newer_than: Option<SystemTime>,
+
+    /// 仅显示在此之前修改的文件（日期如2024-01-01，或时长如7d、24h）
+    #[arg(long, value_name = "TIME", value_parser = parse_time_spec)]
+    pub older_than: Option<SystemTime>,
+
+    /// 仅显示指定扩展名的文件，逗号分隔（如rs,toml,md，不区分大小写）
+    #[arg(long, value_name = "EXTS", value_delimiter = ',')]
+    pub ext: Vec<String>,
+
+    /// 不排除默认忽略的目录（.git、node_modules、target、__pycache__）
+    #[arg(long)]
+    pub no_default_excludes: bool,
+
+    /// 从默认忽略列表中移除指定名称（可重复使用，如--include target）
+    #[arg(long, value_name = "NAME")]
+    pub include: Vec<String>,
+
+    /// 从文件读取排除模式（每行一个通配符模式，支持`*`和`?`，以`/`结尾时仅匹配目录；
+    /// 忽略空行和`#`开头的注释），匹配的条目不再深入遍历
+    #[arg(long, value_name = "FILE")]
+    pub exclude_from: Option<PathBuf>,
+
+    /// 按层级深度为条目循环着色
+    #[arg(long)]
+    pub depth_colors: bool,
+
+    /// 按修改时间为文件着色（1天内绿色、30天内黄色、更早红色）
+    #[arg(long)]
+    pub age_colors: bool,
+
+    /// 在每个目录的子条目之后输出累计大小
+    #[arg(long)]
+    pub dir_totals: bool,
+
+    /// 名称最多显示的字符数，超出时从中间截断并保留扩展名（如`long…name.txt`）
+    #[arg(long, value_name = "N")]
+    pub max_name_width: Option<usize>,
+
+    /// 在目录名后显示直接子条目数量（经过过滤），如`src (3)`
+    #[arg(long)]
+    pub child_count: bool,
+
+    /// 输出格式：auto（默认，终端时为tree，管道或重定向时为paths）、tree、xml、porcelain、
+    /// paths（每行一个路径）、json、jsonl（每行一个JSON对象）、manifest、summary-tree
+    /// 或skeleton（相对路径，目录以`/`结尾）
+    #[arg(long, value_enum, default_value_t = OutputFormat::Auto)]
+    pub format: OutputFormat,
+
+    /// 配合--format json使用，输出带缩进的JSON（默认为单行紧凑格式）
+    #[arg(long)]
+    pub pretty: bool,
+
+    /// 配合--format paths使用，以NUL字符而非换行结束每个路径（同`find -print0`，用于`xargs -0`）
+    #[arg(short = '0', long)]
+    pub null: bool,
+
+    /// 稳定的机器可读格式（等同于--format porcelain），每行为制表符分隔的
+    /// `类型 深度 大小 修改时间 路径`，格式不会随版本做外观上的调整
+    #[arg(long, conflicts_with = "format")]
+    pub porcelain: bool,
+
+    /// 输出文件清单（等同于--format manifest）：每个文件一行`SHA-256摘要  相对路径`，
+    /// 跳过目录和符号链接，无法读取的文件以`!`代替摘要
+    #[arg(long, conflicts_with_all = ["format", "porcelain"])]
+    pub manifest: bool,
+
+    /// 目录汇总视图（等同于--format summary-tree）：每个目录一行，显示其下的文件总数和总大小，
+    /// 不输出文件；--max-depth只限制显示的层级，统计包含更深的内容（类似`du --max-depth`）
+    #[arg(long, conflicts_with_all = ["format", "porcelain", "manifest"])]
+    pub summary_tree: bool,
+
+    /// 通过分页器显示输出（使用$PAGER，默认`less -R`；非终端时直接输出）
+    #[arg(long)]
+    pub pager: bool,
+}
+
+impl TreeArgs {
+    /// 生成目录树的根路径，未指定路径时为当前目录
+    pub fn root(&self) -> &Path {
+        self.path.as_deref().unwrap_or(Path::new("."))
+    }
+
+    /// 根据排序参数确定排序方式和是否倒序
+    ///
+    /// `--sort`、`--type`、`--sort-size`、`--date`和`--no-sort`最多只能指定一个，
+    /// 都未指定时返回`None`，由环境变量或默认值（按名称排序）决定；
+    /// `--reverse`需要排序，不能与不排序同时使用。
+    ///
+    /// # 返回
+    /// * `Ok((Option<SortBy>, Option<bool>))` 显式指定的排序方式和是否倒序
+    /// * `Err(Error::ConflictingArgs)` 指定了多个排序参数时返回
+    pub fn resolve_sort(&self) -> Result<(Option<SortBy>, Option<bool>)> {
+        let selected: Vec<(&str, SortBy)> = [
+            (
+                self.sort.is_some(),
+                "--sort",
+                self.sort.unwrap_or(SortBy::Name),
+            ),
+            (self.type_sort, "--type", SortBy::Type),
+            (self.size_sort, "--sort-size", SortBy::Size),
+            (self.date_sort, "--date", SortBy::Date),
+            (self.no_sort, "--no-sort", SortBy::None),
+        ]
+        .into_iter()
+        .filter(|(given, _, _)| *given)
+        .map(|(_, flag, sort_by)| (flag, sort_by))
+        .collect();
+
+        let sort_by = match selected.as_slice() {
+            [] => None,
+            [(_, sort_by)] => Some(*sort_by),
+            [(first, _), (second, _), ..] => {
+                return Err(Error::ConflictingArgs(format!(
+                    "{} cannot be used with {}",
+                    first, second
+                )))
+            }
+        };
+
+        if self.reverse && sort_by == Some(SortBy::None) {
+            return Err(Error::ConflictingArgs(
+                "--reverse cannot be used without sorting".to_string(),
+            ));
+        }
+        Ok((sort_by, self.reverse.then_some(true)))
+    }
+
+    /// 收集命令行显式指定、需要覆盖环境变量的设置
+    ///
+    /// 未指定的设置为`None`，合并时保留环境变量中的值
+    pub fn overrides(&self) -> Result<ConfigOverrides> {
+        let (sort_by, reverse) = self.resolve_sort()?;
+        Ok(ConfigOverrides {
+            max_depth: self.max_depth,
+            sort_by,
+            reverse,
+        })
+    }
 }
 
 /// 'portown'子命令的参数
-#[derive(Parser)]
+#[derive(Parser, Clone)]
 pub struct PortownArgs {
     /// 显示监听状态端口
     #[arg(short, long)]
@@ -86,11 +413,302 @@ pub struct PortownArgs {
     #[arg(short = 'u', long = "udp")]
     pub udp: bool,
 
-    /// 设置显示深度（进程树层级）
-    #[arg(short = 'd', long)]
+    /// 仅显示--root-pid进程及其后代进程的连接
+    #[arg(long, value_name = "PID")]
+    pub root_pid: Option<u32>,
+
+    /// 配合--root-pid，限制后代进程与根进程之间的层数
+    /// （0仅根进程本身，1包含其直接子进程，依此类推）
+    #[arg(short = 'd', long, requires = "root_pid")]
     pub depth: Option<usize>,
 
     /// 仅显示已建立的连接
     #[arg(short = 'e', long)]
     pub established_only: bool,
+
+    /// 按进程分组显示各进程占用的端口
+    #[arg(long)]
+    pub group_by_process: bool,
+
+    /// 按连接状态分组显示：LISTENING在前，其次ESTABLISHED，最后为其他状态
+    #[arg(long)]
+    pub group_by_state: bool,
+
+    /// 每个进程只显示一行代表连接（优先LISTENING），其余连接折叠为`(+N more)`
+    #[arg(long, conflicts_with = "group_by_process")]
+    pub dedup_process: bool,
+
+    /// 仅显示被多个进程同时占用的端口
+    #[arg(long)]
+    pub conflicts: bool,
+
+    /// 在本地地址后标注常用服务名称（http、https、ssh等）
+    #[arg(long)]
+    pub services: bool,
+
+    /// 仅显示IPv4连接
+    #[arg(long, conflicts_with = "ipv6")]
+    pub ipv4: bool,
+
+    /// 仅显示IPv6连接
+    #[arg(long)]
+    pub ipv6: bool,
+
+    /// 仅显示回环（仅限本机）连接
+    #[arg(long, conflicts_with = "no_loopback")]
+    pub loopback: bool,
+
+    /// 隐藏回环（仅限本机）连接
+    #[arg(long)]
+    pub no_loopback: bool,
+
+    /// 仅显示可从网络访问的监听端口（绑定在0.0.0.0、::或具体网卡地址上），
+    /// 相当于--listen加--no-loopback，用于安全审计
+    #[arg(long, conflicts_with_all = ["established_only", "loopback"])]
+    pub exposed: bool,
+
+    /// 从文件读取保存的netstat输出（`netstat -ano`或`netstat -anp`格式），不执行netstat
+    #[arg(long, value_name = "FILE")]
+    pub input: Option<PathBuf>,
+
+    /// 仅显示远程端口为指定值的连接（如443）
+    #[arg(long, value_name = "PORT")]
+    pub foreign_port: Option<u16>,
+
+    /// 最多显示的连接行数，并在末尾显示总数
+    #[arg(long, value_name = "N")]
+    pub limit: Option<usize>,
+
+    /// 显示进程完整命令行（替代路径列）
+    #[arg(long)]
+    pub cmdline: bool,
+
+    /// 显示进程所属用户
+    #[arg(long)]
+    pub user: bool,
+
+    /// 显示进程已运行时长
+    #[arg(long)]
+    pub uptime: bool,
+
+    /// 显示进程常驻内存和CPU时间
+    #[arg(long)]
+    pub stats: bool,
+
+    /// 内存使用1000进制的SI单位（kB、MB），默认为1024进制（KiB、MiB）
+    #[arg(long)]
+    pub si: bool,
+
+    /// 输出宽度（列数），超出时截断地址、进程名和路径列（默认为终端宽度，非终端时不截断）
+    #[arg(long, value_name = "N")]
+    pub width: Option<usize>,
+
+    /// 输出格式：table（默认）、count（仅输出total、tcp、udp、listening、established统计）
+    /// 或json（连接对象数组）
+    #[arg(long, value_enum, default_value_t = PortownFormat::Table)]
+    pub format: PortownFormat,
+
+    /// 配合--format json使用，输出带缩进的JSON（默认为单行紧凑格式）
+    #[arg(long)]
+    pub pretty: bool,
+
+    /// 仅输出过滤后的连接总数（单个整数），不输出表头和表格
+    #[arg(long, conflicts_with_all = ["format", "group_by_process", "conflicts"])]
+    pub total_only: bool,
+
+    /// 持续监视连接，仅在连接集合变化时刷新表格：新连接以绿色高亮，
+    /// 已关闭的连接以红色显示一次后移除（按Ctrl+C退出）
+    #[arg(long, conflicts_with_all = [
+        "total_only",
+        "format",
+        "group_by_process",
+        "group_by_state",
+        "dedup_process",
+        "conflicts",
+        "copy",
+    ])]
+    pub refresh_on_change: bool,
+
+    /// 将输出（不含颜色）写入指定文件而非标准输出
+    #[arg(long, value_name = "FILE", conflicts_with = "refresh_on_change")]
+    pub output: Option<PathBuf>,
+
+    /// 不为隔行设置背景色（适合浅色终端；也可在配置文件的[theme]中设置stripe）
+    #[arg(long)]
+    pub no_stripes: bool,
+
+    /// 同时将输出（不含颜色）复制到系统剪贴板
+    #[arg(long)]
+    pub copy: bool,
+
+    /// 将连接快照追加写入SQLite数据库的connections表
+    #[cfg(feature = "sqlite")]
+    #[arg(long, value_name = "PATH")]
+    pub sqlite: Option<PathBuf>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree_args(flags: &[&str]) -> TreeArgs {
+        TreeArgs::try_parse_from(std::iter::once("tree").chain(flags.iter().copied())).unwrap()
+    }
+
+    #[test]
+    fn resolve_sort_leaves_unset_flags_empty() {
+        assert_eq!(tree_args(&[]).resolve_sort().unwrap(), (None, None));
+        assert_eq!(
+            tree_args(&["-r"]).resolve_sort().unwrap(),
+            (None, Some(true))
+        );
+    }
+
+    #[test]
+    fn overrides_only_carry_given_flags() {
+        assert_eq!(
+            tree_args(&[]).overrides().unwrap(),
+            ConfigOverrides::default()
+        );
+        assert_eq!(
+            tree_args(&["--sort", "name", "-d", "2"])
+                .overrides()
+                .unwrap(),
+            ConfigOverrides {
+                max_depth: Some(2),
+                sort_by: Some(SortBy::Name),
+                reverse: None,
+            }
+        );
+    }
+
+    #[test]
+    fn size_bounds_accept_units() {
+        assert_eq!(tree_args(&["--min-size", "1K"]).min_size, Some(1024));
+        assert!(TreeArgs::try_parse_from(["tree", "--max-size", "ten"]).is_err());
+    }
+
+    #[test]
+    fn long_version_names_the_target_triple() {
+        let target = LONG_VERSION
+            .lines()
+            .find_map(|line| line.strip_prefix("target: "))
+            .unwrap();
+        // 目标三元组以架构开头，并包含操作系统名称（macOS为darwin）
+        assert!(target.starts_with(std::env::consts::ARCH), "{}", target);
+        let os = match std::env::consts::OS {
+            "macos" => "darwin",
+            os => os,
+        };
+        assert!(target.contains(os), "{}", target);
+    }
+
+    #[test]
+    fn sort_none_is_distinct_from_name() {
+        assert_eq!(
+            tree_args(&["--sort", "none"]).resolve_sort().unwrap(),
+            (Some(SortBy::None), None)
+        );
+        assert_eq!(
+            tree_args(&["--sort", "name"]).resolve_sort().unwrap(),
+            (Some(SortBy::Name), None)
+        );
+        assert_ne!(SortBy::None, SortBy::Name);
+    }
+
+    #[test]
+    fn refresh_on_change_conflicts_with_total_only() {
+        let parse = |flags: &[&str]| {
+            PortownArgs::try_parse_from(std::iter::once("portown").chain(flags.iter().copied()))
+        };
+        assert!(parse(&["--refresh-on-change"]).is_ok());
+        assert!(parse(&["--refresh-on-change", "--total-only"]).is_err());
+    }
+
+    #[test]
+    fn depth_requires_root_pid() {
+        let parse = |flags: &[&str]| {
+            PortownArgs::try_parse_from(std::iter::once("portown").chain(flags.iter().copied()))
+        };
+        assert!(parse(&["--depth", "1"]).is_err());
+        assert!(parse(&["--root-pid", "1", "--depth", "1"]).is_ok());
+    }
+
+    #[test]
+    fn root_defaults_to_current_directory() {
+        assert_eq!(tree_args(&[]).root(), Path::new("."));
+        assert_eq!(tree_args(&["src"]).root(), Path::new("src"));
+    }
+
+    #[test]
+    fn relative_dates_requires_mod() {
+        let parse = |flags: &[&str]| {
+            TreeArgs::try_parse_from(std::iter::once("tree").chain(flags.iter().copied()))
+        };
+        assert!(parse(&["--relative-dates"]).is_err());
+        assert!(parse(&["--relative-dates", "--mod"]).is_ok());
+    }
+
+    #[test]
+    fn only_depth_conflicts_with_max_depth() {
+        let parse = |flags: &[&str]| {
+            TreeArgs::try_parse_from(std::iter::once("tree").chain(flags.iter().copied()))
+        };
+        assert!(parse(&["--only-depth", "1"]).is_ok());
+        assert!(parse(&["--only-depth", "1", "-d", "2"]).is_err());
+    }
+
+    #[test]
+    fn resolve_sort_accepts_a_single_sort_flag() {
+        assert_eq!(
+            tree_args(&["-S", "--reverse"]).resolve_sort().unwrap(),
+            (Some(SortBy::Size), Some(true))
+        );
+        assert_eq!(
+            tree_args(&["--sort", "date"]).resolve_sort().unwrap(),
+            (Some(SortBy::Date), None)
+        );
+        assert_eq!(
+            tree_args(&["--no-sort"]).resolve_sort().unwrap(),
+            (Some(SortBy::None), None)
+        );
+        assert_eq!(
+            tree_args(&["-T"]).resolve_sort().unwrap(),
+            (Some(SortBy::Type), None)
+        );
+    }
+
+    #[test]
+    fn resolve_sort_rejects_conflicting_flags() {
+        let err = tree_args(&["-S", "-t"]).resolve_sort().unwrap_err();
+        assert!(
+            matches!(err, Error::ConflictingArgs(ref msg) if msg == "--sort-size cannot be used with --date")
+        );
+        assert!(tree_args(&["--sort", "name", "--no-sort"])
+            .resolve_sort()
+            .is_err());
+        assert!(matches!(
+            tree_args(&["--no-sort", "-r"]).resolve_sort(),
+            Err(Error::ConflictingArgs(_))
+        ));
+        assert!(tree_args(&["--sort", "none", "-r"]).resolve_sort().is_err());
+    }
+
+    #[test]
+    fn align_sizes_requires_a_size_column() {
+        let parse = |flags: &[&str]| {
+            TreeArgs::try_parse_from(std::iter::once("tree").chain(flags.iter().copied()))
+        };
+        assert!(parse(&["--align-sizes"]).is_err());
+    }
+
+    #[test]
+    fn exposed_conflicts_with_loopback_filters() {
+        let parse = |flags: &[&str]| {
+            PortownArgs::try_parse_from(std::iter::once("portown").chain(flags.iter().copied()))
+        };
+        assert!(parse(&["--exposed"]).is_ok());
+        assert!(parse(&["--exposed", "--loopback"]).is_err());
+        assert!(parse(&["--exposed", "-e"]).is_err());
+    }
 }
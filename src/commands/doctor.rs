@@ -0,0 +1,164 @@
+//! doctor命令实现
+//!
+//! 检查运行环境并输出诊断报告，帮助排查portown等命令在当前平台上无法工作的原因。
+//! 每项检查的结果为通过、警告或失败，存在失败项时命令以非零状态退出。
+
+use crate::config::FileConfig;
+use crate::error::Result;
+use colored::*;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+/// 单项检查的结果
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CheckStatus {
+    /// 检查通过
+    Pass,
+    /// 不影响主要功能的问题
+    Warn,
+    /// 会导致功能不可用的问题
+    Fail,
+}
+
+/// 单项检查及其说明
+#[derive(Debug, Clone)]
+pub struct Check {
+    /// 检查项名称
+    pub name: &'static str,
+    /// 检查结果
+    pub status: CheckStatus,
+    /// 结果说明
+    pub detail: String,
+}
+
+impl Check {
+    fn new(name: &'static str, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// 执行doctor命令，输出诊断报告
+///
+/// # 返回
+/// * `Ok(usize)` - 失败的检查项数量
+pub fn execute() -> Result<usize> {
+    let checks = run_checks();
+    write_report(&checks, &mut io::stdout().lock())?;
+
+    Ok(checks
+        .iter()
+        .filter(|check| check.status == CheckStatus::Fail)
+        .count())
+}
+
+/// 将检查结果逐行写入输出流，每行以带颜色的结果标签开头
+pub fn write_report(checks: &[Check], out: &mut dyn Write) -> io::Result<()> {
+    for check in checks {
+        let label = match check.status {
+            CheckStatus::Pass => "[PASS]".green(),
+            CheckStatus::Warn => "[WARN]".yellow(),
+            CheckStatus::Fail => "[FAIL]".red(),
+        };
+        writeln!(out, "{} {}: {}", label, check.name, check.detail)?;
+    }
+    Ok(())
+}
+
+/// 运行全部环境检查
+pub fn run_checks() -> Vec<Check> {
+    let mut checks = vec![Check::new(
+        "os",
+        CheckStatus::Pass,
+        format!("{} ({})", std::env::consts::OS, std::env::consts::ARCH),
+    )];
+
+    // portown依赖netstat获取连接列表
+    checks.push(match find_in_path("netstat") {
+        Some(path) => Check::new("netstat", CheckStatus::Pass, path.display().to_string()),
+        None => Check::new(
+            "netstat",
+            CheckStatus::Fail,
+            "not found in PATH, portown will not work",
+        ),
+    });
+
+    // 类Unix系统上常见的替代工具，仅作参考
+    if !cfg!(windows) {
+        for tool in ["ss", "lsof"] {
+            checks.push(match find_in_path(tool) {
+                Some(path) => Check::new(tool, CheckStatus::Pass, path.display().to_string()),
+                None => Check::new(tool, CheckStatus::Warn, "not found in PATH (optional)"),
+            });
+        }
+    }
+
+    checks.push(if colored::control::SHOULD_COLORIZE.should_colorize() {
+        Check::new("color", CheckStatus::Pass, "enabled")
+    } else {
+        Check::new(
+            "color",
+            CheckStatus::Warn,
+            "disabled (stdout is not a terminal or --color never)",
+        )
+    });
+
+    checks.push(match FileConfig::path() {
+        None => Check::new(
+            "config",
+            CheckStatus::Warn,
+            "no home directory, config file disabled",
+        ),
+        Some(path) if !path.exists() => Check::new(
+            "config",
+            CheckStatus::Pass,
+            format!("{} not found, using defaults", path.display()),
+        ),
+        Some(path) => match FileConfig::from_path(&path) {
+            Ok(_) => Check::new("config", CheckStatus::Pass, path.display().to_string()),
+            Err(err) => Check::new("config", CheckStatus::Fail, err.to_string()),
+        },
+    });
+
+    checks
+}
+
+/// 在`PATH`中查找可执行文件，Windows上同时尝试`.exe`后缀
+fn find_in_path(program: &str) -> Option<PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths).find_map(|dir| {
+        let candidates = if cfg!(windows) {
+            vec![dir.join(format!("{}.exe", program)), dir.join(program)]
+        } else {
+            vec![dir.join(program)]
+        };
+        candidates.into_iter().find(|path| path.is_file())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_checks_reports_config_status() {
+        assert!(run_checks().iter().any(|check| check.name == "config"));
+    }
+
+    #[test]
+    fn find_in_path_ignores_missing_programs() {
+        assert!(find_in_path("toolboxer-definitely-missing").is_none());
+    }
+
+    #[test]
+    fn report_mentions_the_current_os() {
+        let mut out = Vec::new();
+        write_report(&run_checks(), &mut out).unwrap();
+        let report = String::from_utf8(out).unwrap();
+        assert!(report.contains(std::env::consts::OS), "{}", report);
+        assert!(report.lines().count() >= 3);
+    }
+}
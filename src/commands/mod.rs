@@ -3,12 +3,16 @@
 //! 该模块组织并重新导出各种Toolboxer功能的命令执行器，
 //! 用于统一管理命令行子命令的实现。
 
+/// 包含'doctor'环境诊断命令实现的模块
+pub mod doctor;
 /// 包含'portown'命令实现的模块
 pub mod portown;
 /// 包含'tree'命令实现的模块
 pub mod tree;
 
 // 重新导出命令执行器以便于访问
+/// 重新导出doctor命令的执行函数
+pub use doctor::execute as execute_doctor;
 /// 重新导出portown命令的执行函数
 pub use portown::execute as execute_portown;
 /// 重新导出tree命令的执行函数
@@ -1,283 +1,1992 @@
 use crate::error::Error;
-use std::collections::{HashMap, HashSet};
-use std::io::{self, Write};
+use crate::logging::{self, Level};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::io::{self, IsTerminal, Write};
+use std::net::IpAddr;
+use std::path::PathBuf;
 use std::process::Command;
 use std::sync::Mutex;
-use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+use std::thread;
+use std::time::{Duration, SystemTime};
+use termcolor::{Color, ColorSpec, NoColor, StandardStream, WriteColor};
 
-use crate::cli::PortownArgs;
+use crate::cli::{PortownArgs, PortownFormat};
+use crate::config::{Theme, ThemeColor};
 
-pub fn execute(args: &PortownArgs) -> crate::error::Result<()> {
+/// 单条网络连接及其所属进程信息
+///
+/// 序列化为`--format json`的连接对象，未获取的可选字段不输出
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Connection {
+    /// 协议（TCP/UDP）
+    pub protocol: String,
+    /// 本地地址
+    pub local_address: String,
+    /// 远程地址
+    pub foreign_address: String,
+    /// 连接状态（UDP为"-"）
+    pub state: String,
+    /// 所属进程ID
+    pub pid: String,
+    /// 进程名，无法获取时为"Unknown"
+    pub process_name: String,
+    /// 进程可执行文件路径，无法获取时为"Unknown"
+    pub process_path: String,
+    /// 进程命令行，仅在启用`--cmdline`时获取，无法获取时为"Unknown"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command_line: Option<String>,
+    /// 进程所属用户，仅在启用`--user`时获取，无法获取时为"?"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    /// 进程已运行时长，仅在启用`--uptime`时获取，无法获取时为"?"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uptime: Option<String>,
+    /// 进程常驻内存，仅在启用`--stats`时获取，无法获取时为"?"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory: Option<String>,
+    /// 进程累计CPU时间，仅在启用`--stats`时获取，无法获取时为"?"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu_time: Option<String>,
+}
+
+impl Connection {
+    /// 是否成功解析到所属进程
+    pub fn has_known_process(&self) -> bool {
+        self.process_name != "Unknown"
+    }
+}
+
+/// 获取连接列表的netstat参数
+///
+/// Windows使用`-o`显示PID；Linux使用`-p`显示`PID/进程名`；
+/// 其他平台的netstat不提供PID，进程信息显示为Unknown。
+#[cfg(windows)]
+const NETSTAT_ARGS: &[&str] = &["-ano"];
+#[cfg(target_os = "linux")]
+const NETSTAT_ARGS: &[&str] = &["-anp"];
+#[cfg(not(any(windows, target_os = "linux")))]
+const NETSTAT_ARGS: &[&str] = &["-an"];
+
+/// 执行portown命令，输出连接表格
+///
+/// 协议和连接状态列的颜色取自`theme`
+pub fn execute(args: &PortownArgs, theme: &Theme) -> crate::error::Result<()> {
     use std::time::Instant;
 
+    if args.refresh_on_change {
+        return refresh_on_change(args, theme);
+    }
+
     // 记录开始时间以进行性能测量
     let start_time = Instant::now();
 
-    // 获取TCP/UDP连接信息
-    let output = Command::new("netstat")
-        .args(["-ano"])
-        .output()
-        .map_err(|e| Error::Other(format!("Failed to execute netstat: {}", e)))?;
+    let connections = match &args.output {
+        // 写入文件时不带颜色，只按--width截断
+        Some(path) => {
+            let connections = list_connections(args)?;
+            let mut file = NoColor::new(io::BufWriter::new(std::fs::File::create(path)?));
+            render(&connections, args, theme, args.width, &mut file)?;
+            file.get_mut().flush()?;
+            connections
+        }
+        None => {
+            // 未指定--width时按终端宽度截断，输出被重定向时保留完整内容
+            let width = args.width.or_else(crate::utils::terminal_width);
+            let mut stdout = StandardStream::stdout(crate::utils::color_choice());
+            // 在终端上查看表格时，每个进程查询完成后立即输出对应的行
+            if io::stdout().is_terminal() && width.is_some() && streams_rows(args) {
+                let connections = netstat_connections(connection_source(args).as_ref(), args)?;
+                let mut resolver = SystemResolver::new(args);
+                stream_table(connections, &mut resolver, args, theme, width, &mut stdout)?
+            } else {
+                let connections = list_connections(args)?;
+                render(&connections, args, theme, width, &mut stdout)?;
+                connections
+            }
+        }
+    };
 
-    if !output.status.success() {
-        return Err(Error::Other("netstat command failed".to_string()));
+    #[cfg(feature = "sqlite")]
+    if let Some(path) = &args.sqlite {
+        let db = rusqlite::Connection::open(path)?;
+        export_sqlite(&db, &connections)?;
     }
 
-    let netstat_output = String::from_utf8_lossy(&output.stdout);
+    if args.copy {
+        copy_rendered(&connections, args, theme, &mut SystemClipboard)?;
+    }
 
-    // 收集所有进程信息，避免重复查询
-    let mut pid_cache: HashMap<String, (String, String)> = HashMap::new();
-    let mut connections = Vec::new();
+    // 记录命令执行时间
+    crate::utils::log_command_metrics("portown", start_time.elapsed().as_millis(), "success", None);
 
-    // 解析netstat输出
-    // 在连接处理循环中添加深度过滤
-    let mut current_depth = 0;
+    Ok(())
+}
 
-    for line in netstat_output.lines() {
-        // 应用深度过滤
-        if let Some(max_depth) = args.depth {
-            if current_depth >= max_depth {
-                break;
+/// 按参数选择的模式（进程分组、端口冲突或连接表格）输出连接列表
+///
+/// 整个输出写入同一个`out`，可以是终端、文件或`termcolor::Buffer`等内存缓冲区。
+/// `width`为输出的最大列数，为`None`时不截断。
+pub fn render(
+    connections: &[Connection],
+    args: &PortownArgs,
+    theme: &Theme,
+    width: Option<usize>,
+    out: &mut dyn WriteColor,
+) -> crate::error::Result<()> {
+    if args.total_only {
+        writeln!(out, "{}", connections.len())?;
+        return Ok(());
+    }
+
+    if args.format == PortownFormat::Json {
+        let shown = &connections[..connections.len().min(args.limit.unwrap_or(usize::MAX))];
+        crate::utils::write_json(out, shown, args.pretty)?;
+        return Ok(());
+    }
+
+    if args.format == PortownFormat::Count {
+        let counts = count_connections(connections);
+        writeln!(out, "total={}", counts.total)?;
+        writeln!(out, "tcp={}", counts.tcp)?;
+        writeln!(out, "udp={}", counts.udp)?;
+        writeln!(out, "listening={}", counts.listening)?;
+        writeln!(out, "established={}", counts.established)?;
+        return Ok(());
+    }
+
+    if args.group_by_process {
+        for group in group_by_process(connections) {
+            print_process_group(&group, out)?;
+        }
+        return Ok(());
+    }
+
+    if args.conflicts {
+        return print_conflicts(&find_conflicts(connections), args, theme, width, out);
+    }
+
+    // 已知进程的连接排在未知进程之前
+    let (known_conns, unknown_conns): (Vec<_>, Vec<_>) = connections
+        .iter()
+        .partition(|conn| conn.has_known_process());
+
+    let ordered: Vec<&Connection> = known_conns.into_iter().chain(unknown_conns).collect();
+    let mut rows: Vec<(&Connection, usize)> = if args.dedup_process {
+        dedup_by_process(&ordered)
+    } else {
+        ordered.into_iter().map(|conn| (conn, 0)).collect()
+    };
+    if args.group_by_state {
+        // 稳定排序，每组内部保持已知进程在前的顺序
+        rows.sort_by_key(|(conn, _)| state_group(conn).0);
+    }
+    rows.truncate(args.limit.unwrap_or(usize::MAX));
+
+    // 按实际显示的行计算列宽，再打印表头；已知输出宽度的普通表格
+    // 使用与边查询边输出相同的列宽，保证两种方式的输出逐字节一致
+    let layout = if streams_rows(args) && width.is_some() {
+        Layout::for_streaming(connections, args, width)
+    } else {
+        Layout::measure(rows.iter().map(|(conn, _)| *conn), args, width)
+    };
+    print_header(args, &layout, out)?;
+
+    let stripe = stripe_color(args, theme);
+    let mut shown = 0;
+    let mut current_group = None;
+    for (idx, &(conn, more)) in rows.iter().enumerate() {
+        if args.group_by_state {
+            let (rank, label) = state_group(conn);
+            if current_group != Some(rank) {
+                current_group = Some(rank);
+                print_state_separator(label, out)?;
             }
         }
-        current_depth += 1;
 
-        // 原有过滤逻辑保持不变
-        if args.udp && !line.contains("UDP") {
-            continue;
+        let bg_color = if idx % 2 == 0 { None } else { stripe };
+        print_connection(conn, more, args, theme, bg_color, &layout, out)?;
+        shown += 1;
+    }
+
+    if args.limit.is_some() && logging::enabled(Level::Normal) {
+        print_footer(shown, connections.len(), out)?;
+    }
+
+    Ok(())
+}
+
+/// 连接表格能否边查询进程边输出
+///
+/// 只有不按进程信息分组、折叠或统计的普通表格才能在查询完所有进程之前开始输出。
+fn streams_rows(args: &PortownArgs) -> bool {
+    args.format == PortownFormat::Table
+        && !args.total_only
+        && !args.group_by_process
+        && !args.conflicts
+        && !args.dedup_process
+        && !args.group_by_state
+}
+
+/// 逐个查询进程信息并立即输出对应的行，返回已填入进程信息的连接
+///
+/// 输出与[`render`]逐字节相同：已知进程的连接直接输出，未知进程的连接暂存，
+/// 在所有已知进程之后输出。进程名和用户列的宽度无法预先确定，使用固定宽度
+/// （`render`在相同条件下使用同样的列宽），因此要求`width`不为`None`
+/// （超出列宽的内容会被截断）。
+///
+/// # 参数
+/// * `connections` - 尚未填入进程信息的连接
+/// * `resolver` - 查询进程信息，每个PID只查询一次
+fn stream_table(
+    mut connections: Vec<Connection>,
+    resolver: &mut dyn ProcessResolver,
+    args: &PortownArgs,
+    theme: &Theme,
+    width: Option<usize>,
+    out: &mut dyn WriteColor,
+) -> crate::error::Result<Vec<Connection>> {
+    let layout = Layout::for_streaming(&connections, args, width);
+    print_header(args, &layout, out)?;
+
+    let stripe = stripe_color(args, theme);
+    let limit = args.limit.unwrap_or(usize::MAX);
+    let mut shown = 0;
+    let mut unknown = Vec::new();
+    let mut cache: HashMap<String, ProcessDetails> = HashMap::new();
+    resolver.prepare(&connections);
+    for (idx, conn) in connections.iter_mut().enumerate() {
+        let details = cache
+            .entry(conn.pid.clone())
+            .or_insert_with(|| resolve_process(resolver, &conn.pid, args));
+        details.apply(conn);
+
+        if !conn.has_known_process() {
+            unknown.push(idx);
+        } else if shown < limit {
+            let bg_color = if shown % 2 == 0 { None } else { stripe };
+            print_connection(conn, 0, args, theme, bg_color, &layout, out)?;
+            out.flush()?;
+            shown += 1;
         }
-        if args.tcp && !line.contains("TCP") {
-            continue;
+    }
+
+    for idx in unknown {
+        if shown == limit {
+            break;
         }
-        if !(line.contains("TCP") || line.contains("UDP")) {
-            continue;
+        let bg_color = if shown % 2 == 0 { None } else { stripe };
+        print_connection(&connections[idx], 0, args, theme, bg_color, &layout, out)?;
+        shown += 1;
+    }
+
+    if args.limit.is_some() && logging::enabled(Level::Normal) {
+        print_footer(shown, connections.len(), out)?;
+    }
+
+    Ok(connections)
+}
+
+/// `--refresh-on-change`两次读取连接列表之间的间隔
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 持续监视连接列表，只在连接集合变化时重新输出表格
+///
+/// 新出现的连接以绿色背景高亮，已关闭的连接以红色背景显示一次后移除；
+/// 连接未变化时不重绘，避免闪烁。按Ctrl+C退出。
+fn refresh_on_change(args: &PortownArgs, theme: &Theme) -> crate::error::Result<()> {
+    let mut stdout = StandardStream::stdout(crate::utils::color_choice());
+    let mut previous: Option<Vec<Connection>> = None;
+    let mut highlighted = false;
+
+    loop {
+        let current = list_connections(args)?;
+        // 第一次快照不标记任何变化
+        let changes = diff_connections(previous.as_deref().unwrap_or(&current), &current);
+        let has_changes = changes
+            .iter()
+            .any(|(_, change)| *change != ConnectionChange::Unchanged);
+
+        // 上一次输出带有高亮时需要再重绘一次，去掉已关闭的连接和高亮
+        if previous.is_none() || has_changes || highlighted {
+            // 重定向到文件时按顺序追加输出，不写入清屏序列
+            if io::stdout().is_terminal() {
+                write!(stdout, "\x1B[2J\x1B[H")?;
+            }
+            let width = args.width.or_else(crate::utils::terminal_width);
+            render_changes(&changes, args, theme, width, &mut stdout)?;
+            stdout.flush()?;
+            highlighted = has_changes;
+        }
+
+        previous = Some(current);
+        thread::sleep(REFRESH_INTERVAL);
+    }
+}
+
+/// 输出带变化标记的连接表格，末尾附带新增和关闭的连接数
+fn render_changes(
+    changes: &[(&Connection, ConnectionChange)],
+    args: &PortownArgs,
+    theme: &Theme,
+    width: Option<usize>,
+    out: &mut dyn WriteColor,
+) -> crate::error::Result<()> {
+    let rows = &changes[..changes.len().min(args.limit.unwrap_or(usize::MAX))];
+    let layout = Layout::measure(rows.iter().map(|(conn, _)| *conn), args, width);
+    print_header(args, &layout, out)?;
+
+    let stripe = stripe_color(args, theme);
+    for (idx, (conn, change)) in rows.iter().enumerate() {
+        let bg_color = match change {
+            ConnectionChange::Added => Some(Color::Ansi256(22)),
+            ConnectionChange::Closed => Some(Color::Ansi256(52)),
+            ConnectionChange::Unchanged if idx % 2 == 1 => stripe,
+            ConnectionChange::Unchanged => None,
+        };
+        print_connection(conn, 0, args, theme, bg_color, &layout, out)?;
+    }
+
+    let count = |kind| changes.iter().filter(|(_, change)| *change == kind).count();
+    let (added, closed) = (
+        count(ConnectionChange::Added),
+        count(ConnectionChange::Closed),
+    );
+    if added > 0 || closed > 0 {
+        out.set_color(ColorSpec::new().set_dimmed(true))?;
+        writeln!(out, "\n+{} new, -{} closed", added, closed)?;
+        out.reset()?;
+    }
+
+    Ok(())
+}
+
+/// 将连接快照追加写入SQLite数据库
+///
+/// 表`connections`不存在时自动创建，每行对应一条连接，
+/// 同一次调用写入的行共享相同的`captured_at`（Unix时间戳，秒）。
+///
+/// # 参数
+/// * `db` - 已打开的数据库连接
+/// * `connections` - 要写入的连接列表
+#[cfg(feature = "sqlite")]
+pub fn export_sqlite(
+    db: &rusqlite::Connection,
+    connections: &[Connection],
+) -> crate::error::Result<()> {
+    db.execute_batch(
+        "CREATE TABLE IF NOT EXISTS connections (
+            id INTEGER PRIMARY KEY,
+            captured_at INTEGER NOT NULL,
+            protocol TEXT NOT NULL,
+            local_address TEXT NOT NULL,
+            foreign_address TEXT NOT NULL,
+            state TEXT NOT NULL,
+            pid TEXT NOT NULL,
+            process_name TEXT NOT NULL,
+            process_path TEXT NOT NULL,
+            command_line TEXT,
+            user TEXT,
+            uptime TEXT,
+            memory TEXT,
+            cpu_time TEXT
+        )",
+    )?;
+
+    let captured_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+
+    let tx = db.unchecked_transaction()?;
+    {
+        let mut insert = tx.prepare(
+            "INSERT INTO connections (captured_at, protocol, local_address, foreign_address,
+                state, pid, process_name, process_path, command_line, user, uptime, memory,
+                cpu_time)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+        )?;
+        for conn in connections {
+            insert.execute(rusqlite::params![
+                captured_at,
+                conn.protocol,
+                conn.local_address,
+                conn.foreign_address,
+                conn.state,
+                conn.pid,
+                conn.process_name,
+                conn.process_path,
+                conn.command_line,
+                conn.user,
+                conn.uptime,
+                conn.memory,
+                conn.cpu_time,
+            ])?;
+        }
+    }
+    tx.commit()?;
+
+    Ok(())
+}
+
+/// `--copy`写入的剪贴板
+///
+/// 默认实现[`SystemClipboard`]访问系统剪贴板；测试使用记录写入内容的实现。
+trait Clipboard {
+    /// 将剪贴板内容替换为`text`
+    fn set_text(&mut self, text: &str) -> crate::error::Result<()>;
+}
+
+/// 通过arboard访问的系统剪贴板
+struct SystemClipboard;
+
+impl Clipboard for SystemClipboard {
+    fn set_text(&mut self, text: &str) -> crate::error::Result<()> {
+        arboard::Clipboard::new()
+            .and_then(|mut clipboard| clipboard.set_text(text))
+            .map_err(|err| Error::Other(err.to_string()))
+    }
+}
+
+/// 以无颜色的形式再渲染一次连接列表并复制到剪贴板
+///
+/// 复制失败时（如无图形环境）输出警告后继续，只有渲染失败才返回错误。
+fn copy_rendered(
+    connections: &[Connection],
+    args: &PortownArgs,
+    theme: &Theme,
+    clipboard: &mut dyn Clipboard,
+) -> crate::error::Result<()> {
+    let mut plain = NoColor::new(Vec::new());
+    render(connections, args, theme, args.width, &mut plain)?;
+    if let Err(err) = clipboard.set_text(&String::from_utf8_lossy(&plain.into_inner())) {
+        logging::warn(format!("failed to copy to clipboard: {}", err));
+    }
+    Ok(())
+}
+
+/// `--format count`输出的连接统计
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConnectionCounts {
+    /// 连接总数
+    pub total: usize,
+    /// TCP连接数
+    pub tcp: usize,
+    /// UDP连接数
+    pub udp: usize,
+    /// 处于LISTENING状态的连接数
+    pub listening: usize,
+    /// 处于ESTABLISHED状态的连接数
+    pub established: usize,
+}
+
+/// 统计连接数量（按协议和状态）
+///
+/// # 参数
+/// * `connections` - 已过滤的连接列表
+pub fn count_connections(connections: &[Connection]) -> ConnectionCounts {
+    let mut counts = ConnectionCounts {
+        total: connections.len(),
+        ..ConnectionCounts::default()
+    };
+    for conn in connections {
+        match conn.protocol.as_str() {
+            "TCP" => counts.tcp += 1,
+            "UDP" => counts.udp += 1,
+            _ => {}
+        }
+        match conn.state.as_str() {
+            "LISTENING" => counts.listening += 1,
+            "ESTABLISHED" => counts.established += 1,
+            _ => {}
+        }
+    }
+    counts
+}
+
+/// 按进程聚合的端口占用信息
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessGroup {
+    /// 进程ID
+    pub pid: String,
+    /// 进程名
+    pub name: String,
+    /// 进程占用的本地端口（去重并升序排列）
+    pub ports: Vec<u16>,
+}
+
+/// 将连接按PID聚合，结果按端口数量降序排列
+///
+/// 端口数量相同的进程保持其首次出现的顺序。
+///
+/// # 参数
+/// * `connections` - 已解析进程信息的连接列表
+pub fn group_by_process(connections: &[Connection]) -> Vec<ProcessGroup> {
+    let mut order: Vec<&str> = Vec::new();
+    let mut groups: HashMap<&str, (&str, BTreeSet<u16>)> = HashMap::new();
+
+    for conn in connections {
+        let group = groups.entry(&conn.pid).or_insert_with(|| {
+            order.push(&conn.pid);
+            (&conn.process_name, BTreeSet::new())
+        });
+        if let Some(port) = parse_port(&conn.local_address) {
+            group.1.insert(port);
         }
+    }
 
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        // Windows的netstat输出中TCP和UDP的列数不同
-        let min_columns = if line.contains("TCP") { 5 } else { 4 };
-        if parts.len() < min_columns {
+    let mut result: Vec<ProcessGroup> = order
+        .into_iter()
+        .map(|pid| {
+            let (name, ports) = &groups[pid];
+            ProcessGroup {
+                pid: pid.to_string(),
+                name: name.to_string(),
+                ports: ports.iter().copied().collect(),
+            }
+        })
+        .collect();
+    result.sort_by_key(|group| std::cmp::Reverse(group.ports.len()));
+    result
+}
+
+/// 将同一PID的多条连接折叠为一行代表连接（`--dedup-process`）
+///
+/// 代表连接优先选择该进程的LISTENING连接，否则为首次出现的连接；
+/// 返回的每项附带被折叠的连接数，顺序为各PID首次出现的顺序。
+/// 未解析到进程的连接无法判断归属，保持逐行显示。
+///
+/// # 参数
+/// * `connections` - 按显示顺序排列的连接
+pub fn dedup_by_process<'a>(connections: &[&'a Connection]) -> Vec<(&'a Connection, usize)> {
+    let mut rows: Vec<(&Connection, usize)> = Vec::new();
+    let mut index: HashMap<&str, usize> = HashMap::new();
+
+    for &conn in connections {
+        if !conn.has_known_process() {
+            rows.push((conn, 0));
             continue;
         }
+        match index.get(conn.pid.as_str()) {
+            Some(&i) => {
+                let row = &mut rows[i];
+                if row.0.state != "LISTENING" && conn.state == "LISTENING" {
+                    row.0 = conn;
+                }
+                row.1 += 1;
+            }
+            None => {
+                index.insert(&conn.pid, rows.len());
+                rows.push((conn, 0));
+            }
+        }
+    }
+
+    rows
+}
 
-        let protocol = if line.contains("TCP") {
-            "TCP".to_string()
+/// 被多个进程同时绑定的本地端口
+#[derive(Debug, Clone, PartialEq)]
+pub struct PortConflict {
+    /// 协议（TCP/UDP）
+    pub protocol: String,
+    /// 冲突的本地端口
+    pub port: u16,
+    /// 绑定该端口的所有连接
+    pub connections: Vec<Connection>,
+}
+
+/// 查找被一个以上不同PID绑定的(协议, 本地端口)组合
+///
+/// 结果按协议和端口排序。
+///
+/// # 参数
+/// * `connections` - 已过滤的连接列表
+pub fn find_conflicts(connections: &[Connection]) -> Vec<PortConflict> {
+    let mut bindings: BTreeMap<(&str, u16), Vec<&Connection>> = BTreeMap::new();
+    for conn in connections {
+        if let Some(port) = parse_port(&conn.local_address) {
+            bindings
+                .entry((&conn.protocol, port))
+                .or_default()
+                .push(conn);
+        }
+    }
+
+    bindings
+        .into_iter()
+        .filter(|(_, conns)| {
+            let pids: HashSet<&str> = conns.iter().map(|conn| conn.pid.as_str()).collect();
+            pids.len() > 1
+        })
+        .map(|((protocol, port), conns)| PortConflict {
+            protocol: protocol.to_string(),
+            port,
+            connections: conns.into_iter().cloned().collect(),
+        })
+        .collect()
+}
+
+/// 两次连接快照之间单条连接的变化
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConnectionChange {
+    /// 仅出现在新快照中
+    Added,
+    /// 仅出现在旧快照中（已关闭）
+    Closed,
+    /// 两次快照中都存在
+    Unchanged,
+}
+
+/// 比较两次连接快照，按（协议、本地地址、远程地址、PID）识别同一连接
+///
+/// 返回新快照中的连接（标记为`Added`或`Unchanged`，保持原顺序），
+/// 其后是旧快照中已关闭的连接（标记为`Closed`）。
+///
+/// # 参数
+/// * `previous` - 上一次的快照
+/// * `current` - 本次的快照
+pub fn diff_connections<'a>(
+    previous: &'a [Connection],
+    current: &'a [Connection],
+) -> Vec<(&'a Connection, ConnectionChange)> {
+    fn key(conn: &Connection) -> (&str, &str, &str, &str) {
+        (
+            &conn.protocol,
+            &conn.local_address,
+            &conn.foreign_address,
+            &conn.pid,
+        )
+    }
+
+    let before: HashSet<_> = previous.iter().map(key).collect();
+    let after: HashSet<_> = current.iter().map(key).collect();
+
+    let current_rows = current.iter().map(|conn| {
+        let change = if before.contains(&key(conn)) {
+            ConnectionChange::Unchanged
         } else {
-            "UDP".to_string()
+            ConnectionChange::Added
         };
-        let local_address = parts[1].to_string();
-        let foreign_address = parts[2].to_string();
-        // UDP connections don't have state in Windows netstat
-        let state = if line.contains("TCP") {
-            parts[3].to_string()
-        } else {
-            "-".to_string()
+        (conn, change)
+    });
+    let closed_rows = previous
+        .iter()
+        .filter(|conn| !after.contains(&key(conn)))
+        .map(|conn| (conn, ConnectionChange::Closed));
+    current_rows.chain(closed_rows).collect()
+}
+
+/// 将`地址:端口`拆分为主机和端口两部分
+///
+/// 以最后一个冒号分隔，因此`[::]:443`这样的IPv6地址也能正确拆分，
+/// 主机部分的方括号会被去除。
+fn split_address(address: &str) -> Option<(&str, &str)> {
+    let (host, port) = address.rsplit_once(':')?;
+    let host = host
+        .strip_prefix('[')
+        .and_then(|h| h.strip_suffix(']'))
+        .unwrap_or(host);
+    Some((host, port))
+}
+
+/// 连接地址所属的地址族
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AddressFamily {
+    /// IPv4地址，例如`0.0.0.0:80`
+    Ipv4,
+    /// IPv6地址，例如`[::1]:443`
+    Ipv6,
+    /// 无法识别，例如UDP的`*:*`
+    Unknown,
+}
+
+/// 根据地址格式判断地址族
+///
+/// 带方括号或主机部分含冒号的为IPv6，含点号的为IPv4
+///
+/// # 参数
+/// * `address` - `地址:端口`形式的地址
+pub fn address_family(address: &str) -> AddressFamily {
+    if address.starts_with('[') {
+        return AddressFamily::Ipv6;
+    }
+
+    match split_address(address) {
+        Some((host, _)) if host.contains(':') => AddressFamily::Ipv6,
+        Some((host, _)) if host.contains('.') => AddressFamily::Ipv4,
+        _ => AddressFamily::Unknown,
+    }
+}
+
+/// 判断连接是否只在本机内部通信
+///
+/// 本地地址为`127.0.0.0/8`或`::1`时为回环连接；
+/// 绑定在`0.0.0.0`/`::`上但对端为回环地址的连接同样视为回环连接。
+pub fn is_loopback(conn: &Connection) -> bool {
+    match parse_ip(&conn.local_address) {
+        Some(ip) if ip.is_loopback() => true,
+        Some(ip) if ip.is_unspecified() => {
+            parse_ip(&conn.foreign_address).is_some_and(|foreign| foreign.is_loopback())
+        }
+        _ => false,
+    }
+}
+
+/// 从`地址:端口`形式的地址中解析IP地址，忽略IPv6的区域标识（`%12`）
+fn parse_ip(address: &str) -> Option<IpAddr> {
+    let (host, _) = split_address(address)?;
+    host.split('%').next()?.parse().ok()
+}
+
+/// 从`地址:端口`形式的地址中提取端口号
+///
+/// 端口为`*`或无法解析时返回`None`
+fn parse_port(address: &str) -> Option<u16> {
+    split_address(address)?.1.parse().ok()
+}
+
+/// 判断进程是否为`root`本身或其后代，且与`root`相距不超过`max_depth`层
+///
+/// 从`pid`开始沿父进程链向上查找`root`，链断开（父进程未知或指向自身）时返回`false`。
+///
+/// # 参数
+/// * `pid` - 要检查的进程ID
+/// * `root` - 进程树的根进程ID
+/// * `max_depth` - 允许的最大层数，0表示只匹配`root`本身
+/// * `parents` - 进程ID到父进程ID的映射
+pub fn within_ancestry(
+    pid: &str,
+    root: &str,
+    max_depth: usize,
+    parents: &HashMap<String, String>,
+) -> bool {
+    let mut current = pid;
+    let mut hops = 0;
+    loop {
+        if current == root {
+            return true;
+        }
+        if hops == max_depth {
+            return false;
+        }
+        match parents.get(current) {
+            Some(parent) if parent != current => current = parent,
+            _ => return false,
+        }
+        hops += 1;
+    }
+}
+
+/// 连接列表的数据来源，提供`netstat`格式的原始输出
+///
+/// 默认运行系统的netstat（[`Netstat`]），指定`--input`时读取保存的输出（[`NetstatFile`]）；
+/// 库的使用者可以提供自己的实现，例如从远程主机采集的输出。
+pub trait ConnectionSource {
+    /// 读取`netstat`格式的连接列表文本
+    fn netstat_output(&self) -> crate::error::Result<String>;
+}
+
+/// 运行系统的netstat命令获取连接列表
+pub struct Netstat;
+
+impl ConnectionSource for Netstat {
+    fn netstat_output(&self) -> crate::error::Result<String> {
+        let output = Command::new("netstat")
+            .args(NETSTAT_ARGS)
+            .output()
+            .map_err(|e| Error::Other(format!("Failed to execute netstat: {}", e)))?;
+
+        if !output.status.success() {
+            return Err(Error::Other("netstat command failed".to_string()));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+/// 读取保存的netstat输出文件（`--input`），不启动netstat
+pub struct NetstatFile(pub PathBuf);
+
+impl ConnectionSource for NetstatFile {
+    fn netstat_output(&self) -> crate::error::Result<String> {
+        let bytes = std::fs::read(&self.0).map_err(|_| Error::PathAccess(self.0.clone()))?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+}
+
+/// 按参数选择连接列表的来源
+fn connection_source(args: &PortownArgs) -> Box<dyn ConnectionSource> {
+    match &args.input {
+        Some(path) => Box::new(NetstatFile(path.clone())),
+        None => Box::new(Netstat),
+    }
+}
+
+/// 获取经过过滤的连接列表及其所属进程信息，不产生任何输出
+///
+/// # 参数
+/// * `args` - 协议、状态等过滤条件
+///
+/// # 返回
+/// * `Ok(Vec<Connection>)` 按netstat输出顺序排列的连接
+/// * `Err(Error)` netstat执行失败或`--input`文件无法读取时返回
+pub fn list_connections(args: &PortownArgs) -> crate::error::Result<Vec<Connection>> {
+    list_connections_from(connection_source(args).as_ref(), args)
+}
+
+/// 与[`list_connections`]相同，但从指定的来源读取连接列表
+///
+/// 进程信息仍从本机查询。
+pub fn list_connections_from(
+    source: &dyn ConnectionSource,
+    args: &PortownArgs,
+) -> crate::error::Result<Vec<Connection>> {
+    resolve_connections(source, &mut SystemResolver::new(args), args)
+}
+
+/// 从`source`读取连接列表，并通过`resolver`填入进程信息
+fn resolve_connections(
+    source: &dyn ConnectionSource,
+    resolver: &mut dyn ProcessResolver,
+    args: &PortownArgs,
+) -> crate::error::Result<Vec<Connection>> {
+    let mut connections = netstat_connections(source, args)?;
+    fill_process_info(&mut connections, resolver, args);
+    Ok(connections)
+}
+
+/// 获取经过过滤的连接列表，进程信息字段留空待后续填充
+fn netstat_connections(
+    source: &dyn ConnectionSource,
+    args: &PortownArgs,
+) -> crate::error::Result<Vec<Connection>> {
+    let mut connections = parse_connections(&source.netstat_output()?, args);
+
+    // 按进程树过滤，在查询进程信息之前进行以减少查询次数
+    if let Some(root) = args.root_pid {
+        let parents = get_parent_pids();
+        let root = root.to_string();
+        let max_depth = args.depth.unwrap_or(usize::MAX);
+        connections.retain(|conn| within_ancestry(&conn.pid, &root, max_depth, &parents));
+    }
+
+    Ok(connections)
+}
+
+/// 按PID查询进程的原始信息
+///
+/// 默认实现[`SystemResolver`]查询操作系统；测试使用返回固定信息的实现。
+/// 无法获取的信息返回`None`，由[`resolve_process`]统一格式化并替换为占位符。
+trait ProcessResolver {
+    /// 在逐个查询之前以全部连接调用一次，可用于批量查询
+    fn prepare(&mut self, _connections: &[Connection]) {}
+
+    /// 计算进程运行时长时使用的当前时间，同一次输出中保持不变
+    fn now(&self) -> SystemTime;
+
+    /// 进程名和可执行文件路径
+    fn name_and_path(&mut self, pid: &str) -> Option<(String, String)>;
+
+    /// 进程命令行
+    fn command_line(&mut self, pid: &str) -> Option<String>;
+
+    /// 进程所属用户
+    fn user(&mut self, pid: &str) -> Option<String>;
+
+    /// 进程启动时间
+    fn start_time(&mut self, pid: &str) -> Option<SystemTime>;
+
+    /// 进程的内存和CPU占用
+    fn stats(&mut self, pid: &str) -> Option<ProcessStats>;
+}
+
+/// 按参数从操作系统查询进程信息
+///
+/// Windows上命令行为一次批量查询（在`prepare`中完成），其余信息按PID逐个获取。
+struct SystemResolver<'a> {
+    args: &'a PortownArgs,
+    command_lines: HashMap<String, String>,
+    now: SystemTime,
+}
+
+impl<'a> SystemResolver<'a> {
+    fn new(args: &'a PortownArgs) -> Self {
+        Self {
+            args,
+            command_lines: HashMap::new(),
+            now: SystemTime::now(),
+        }
+    }
+}
+
+impl ProcessResolver for SystemResolver<'_> {
+    fn prepare(&mut self, connections: &[Connection]) {
+        if self.args.cmdline {
+            let unique_pids: HashSet<String> =
+                connections.iter().map(|conn| conn.pid.clone()).collect();
+            self.command_lines = get_command_lines(&unique_pids);
+        }
+    }
+
+    fn now(&self) -> SystemTime {
+        self.now
+    }
+
+    fn name_and_path(&mut self, pid: &str) -> Option<(String, String)> {
+        get_process_info(pid).ok()
+    }
+
+    fn command_line(&mut self, pid: &str) -> Option<String> {
+        self.command_lines.get(pid).cloned()
+    }
+
+    fn user(&mut self, pid: &str) -> Option<String> {
+        get_process_user(pid)
+    }
+
+    fn start_time(&mut self, pid: &str) -> Option<SystemTime> {
+        get_process_start_time(pid)
+    }
+
+    fn stats(&mut self, pid: &str) -> Option<ProcessStats> {
+        get_process_stats(pid)
+    }
+}
+
+/// 一个进程需要显示的信息，由该进程的所有连接共用
+#[derive(Debug, Clone, PartialEq)]
+struct ProcessDetails {
+    name: String,
+    path: String,
+    command_line: Option<String>,
+    user: Option<String>,
+    uptime: Option<String>,
+    memory: Option<String>,
+    cpu_time: Option<String>,
+}
+
+impl ProcessDetails {
+    /// 将进程信息写入连接
+    fn apply(&self, conn: &mut Connection) {
+        conn.process_name = self.name.clone();
+        conn.process_path = self.path.clone();
+        conn.command_line = self.command_line.clone();
+        conn.user = self.user.clone();
+        conn.uptime = self.uptime.clone();
+        conn.memory = self.memory.clone();
+        conn.cpu_time = self.cpu_time.clone();
+    }
+}
+
+/// 按参数查询单个进程需要显示的信息，无法获取的字段使用"Unknown"或"?"占位
+fn resolve_process(
+    resolver: &mut dyn ProcessResolver,
+    pid: &str,
+    args: &PortownArgs,
+) -> ProcessDetails {
+    let (name, path) = resolver
+        .name_and_path(pid)
+        .unwrap_or_else(|| ("Unknown".to_string(), "Unknown".to_string()));
+    let stats = args.stats.then(|| resolver.stats(pid));
+    let now = resolver.now();
+    ProcessDetails {
+        name,
+        path,
+        command_line: args.cmdline.then(|| {
+            resolver
+                .command_line(pid)
+                .unwrap_or_else(|| "Unknown".to_string())
+        }),
+        user: args
+            .user
+            .then(|| resolver.user(pid).unwrap_or_else(|| "?".to_string())),
+        uptime: args.uptime.then(|| {
+            resolver
+                .start_time(pid)
+                .and_then(|start| now.duration_since(start).ok())
+                .map_or_else(|| "?".to_string(), crate::utils::format_duration)
+        }),
+        memory: stats.map(|stats| {
+            stats.map_or_else(
+                || "?".to_string(),
+                |s| crate::utils::format_size_units(s.resident_bytes, !args.si),
+            )
+        }),
+        cpu_time: stats.map(|stats| {
+            stats.map_or_else(
+                || "?".to_string(),
+                |s| crate::utils::format_duration(s.cpu_time),
+            )
+        }),
+    }
+}
+
+/// 查询进程信息并填入连接，每个PID只查询一次
+///
+/// 按PID在连接列表中首次出现的顺序查询，同一进程的其他连接复用结果。
+fn fill_process_info(
+    connections: &mut [Connection],
+    resolver: &mut dyn ProcessResolver,
+    args: &PortownArgs,
+) {
+    resolver.prepare(connections);
+    let mut cache: HashMap<String, ProcessDetails> = HashMap::new();
+    for conn in connections.iter_mut() {
+        let details = cache
+            .entry(conn.pid.clone())
+            .or_insert_with(|| resolve_process(resolver, &conn.pid, args));
+        details.apply(conn);
+    }
+}
+
+/// 解析netstat输出并按参数过滤，进程信息字段留空待后续填充
+///
+/// # 参数
+/// * `netstat_output` - `netstat -ano`的原始输出
+/// * `args` - 协议、状态等过滤条件
+pub fn parse_connections(netstat_output: &str, args: &PortownArgs) -> Vec<Connection> {
+    let mut connections = Vec::new();
+    // 各过滤阶段后剩余的条目数：原始行、解析成功、协议、状态、地址族、回环、远程端口
+    let mut stages = [0usize; 7];
+    let skip = |reason: &str, line: &str| {
+        logging::debug(format!("skipped ({}): {}", reason, line.trim()));
+    };
+
+    // 解析netstat输出
+    for line in netstat_output.lines() {
+        stages[0] += 1;
+
+        let Some(conn) = parse_line(line) else {
+            continue;
         };
+        stages[1] += 1;
 
-        // 根据参数过滤连接状态
-        if args.listen && state != "LISTENING" {
+        // 根据参数过滤协议和连接状态
+        if (args.udp && conn.protocol != "UDP") || (args.tcp && conn.protocol != "TCP") {
+            skip("protocol", line);
             continue;
         }
-        if args.established_only && state != "ESTABLISHED" {
+        stages[2] += 1;
+        if ((args.listen || args.exposed) && conn.state != "LISTENING")
+            || (args.established_only && conn.state != "ESTABLISHED")
+        {
+            skip("state", line);
             continue;
         }
+        stages[3] += 1;
 
-        // PID在TCP和UDP输出中位于不同列
-        let pid = if line.contains("TCP") {
-            parts[4].to_string()
-        } else {
-            parts[3].to_string()
-        };
+        // 根据本地地址格式过滤地址族
+        let family = address_family(&conn.local_address);
+        if (args.ipv4 && family != AddressFamily::Ipv4)
+            || (args.ipv6 && family != AddressFamily::Ipv6)
+        {
+            skip("address family", line);
+            continue;
+        }
+        stages[4] += 1;
 
-        connections.push((protocol, local_address, foreign_address, state, pid));
-    }
+        // 根据是否为回环连接过滤，--exposed只保留非回环地址上的监听
+        if (args.loopback && !is_loopback(&conn))
+            || ((args.no_loopback || args.exposed) && is_loopback(&conn))
+        {
+            skip("loopback", line);
+            continue;
+        }
+        stages[5] += 1;
 
-    // 获取所有进程信息（去重后）
-    let unique_pids: HashSet<_> = connections.iter().map(|(_, _, _, _, pid)| pid).collect();
-    for pid in unique_pids {
-        if !pid_cache.contains_key(pid) {
-            match get_process_info(pid) {
-                Ok(info) => {
-                    pid_cache.insert(pid.to_string(), info);
-                }
-                Err(_) => {
-                    pid_cache.insert(
-                        pid.to_string(),
-                        ("Unknown".to_string(), "Unknown".to_string()),
-                    );
-                }
+        // 根据远程端口过滤，`*:*`等无端口的地址不匹配
+        if let Some(port) = args.foreign_port {
+            if parse_port(&conn.foreign_address) != Some(port) {
+                skip("foreign port", line);
+                continue;
             }
         }
+        stages[6] += 1;
+
+        connections.push(conn);
     }
 
-    // 打印表头
-    print_header()?;
-
-    // 分离已知和未知进程连接
-    let (known_conns, unknown_conns): (Vec<_>, Vec<_>) =
-        connections.iter().partition(|(_, _, _, _, pid)| {
-            pid_cache
-                .get(pid)
-                .map(|(name, _)| name != "Unknown")
-                .unwrap_or(false)
-        });
+    logging::info(format!(
+            "netstat lines: {}, parsed: {}, after protocol: {}, after state: {}, after address family: {}, after loopback: {}, after foreign port: {}",
+            stages[0], stages[1], stages[2], stages[3], stages[4], stages[5], stages[6]
+    ));
 
-    // 打印已知进程连接
-    for (idx, (protocol, local_address, foreign_address, state, pid)) in
-        known_conns.iter().enumerate()
-    {
-        let (name, path) = pid_cache.get(pid).unwrap();
-        let bg_color = if idx % 2 == 0 {
-            None
-        } else {
-            Some(Color::Ansi256(236))
-        };
-        print_connection(
-            protocol,
-            local_address,
-            foreign_address,
-            state,
-            pid,
-            name,
-            path,
-            bg_color,
-        )?;
+    connections
+}
+
+/// 解析单行netstat输出，表头等非连接行返回`None`
+///
+/// 协议取自第一列；PID取自最后一列，TCP的状态为地址与PID之间的部分，
+/// 因此不受本地化状态文本和列宽变化的影响。状态会经过`normalize_state`
+/// 转换为英文名称。同时支持Linux `netstat -anp`带Recv-Q/Send-Q列的格式。
+///
+/// # 参数
+/// * `line` - 单行netstat输出，例如`TCP [::]:443 [::]:0 LISTENING 4`
+///   或`tcp 0 0 0.0.0.0:22 0.0.0.0:* LISTEN 812/sshd`
+pub fn parse_line(line: &str) -> Option<Connection> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+
+    let protocol = parts.first()?.to_ascii_uppercase();
+    let protocol = if protocol.starts_with("TCP") {
+        "TCP"
+    } else if protocol.starts_with("UDP") {
+        "UDP"
+    } else {
+        return None;
+    };
+
+    // Linux的netstat在协议后有Recv-Q、Send-Q两列数字
+    let offset = if parts.get(1).is_some_and(|p| p.parse::<u64>().is_ok()) {
+        2
+    } else {
+        0
+    };
+
+    // UDP没有状态列
+    let min_columns = offset + if protocol == "TCP" { 5 } else { 4 };
+    if parts.len() < min_columns {
+        return None;
     }
 
-    // 打印未知进程连接
-    for (idx, (protocol, local_address, foreign_address, state, pid)) in
-        unknown_conns.iter().enumerate()
-    {
-        let (name, path) = pid_cache.get(pid).unwrap();
-        let bg_color = if (known_conns.len() + idx) % 2 == 0 {
-            None
-        } else {
-            Some(Color::Ansi256(236))
+    // Linux的PID列格式为`PID/进程名`，无权限时为`-`
+    let pid = parts[parts.len() - 1].split('/').next().unwrap_or("-");
+    let state = if protocol == "TCP" {
+        normalize_state(&parts[offset + 3..parts.len() - 1].join(" "))
+    } else {
+        "-".to_string()
+    };
+
+    Some(Connection {
+        protocol: protocol.to_string(),
+        local_address: parts[offset + 1].to_string(),
+        foreign_address: parts[offset + 2].to_string(),
+        state,
+        pid: pid.to_string(),
+        process_name: String::new(),
+        process_path: String::new(),
+        command_line: None,
+        user: None,
+        uptime: None,
+        memory: None,
+        cpu_time: None,
+    })
+}
+
+/// 将本地化的TCP状态文本转换为netstat的英文状态名
+///
+/// 覆盖德语、法语、西班牙语、意大利语等常见Windows语言版本，未知状态原样返回（转为大写）。
+/// netstat输出使用OEM代码页，非ASCII字符在解码后可能变成替换字符，
+/// 因此只比较状态文本中的ASCII字符（例如`ABHÖREN`按`ABHREN`匹配）。
+pub fn normalize_state(state: &str) -> String {
+    let upper = state.to_uppercase();
+    let key: String = upper.chars().filter(|c| c.is_ascii()).collect();
+    let normalized = match key.as_str() {
+        "LISTEN" | "ABHREN" | "COUTE" | "ESCUCHANDO" | "IN ASCOLTO" => "LISTENING",
+        "HERGESTELLT" | "TABLIE" | "ESTABLECIDO" | "ESTABLECIDA" | "STABILITA" => "ESTABLISHED",
+        "WARTEND" | "ATTENTE_TEMPS" | "TIEMPO_ESPERA" => "TIME_WAIT",
+        "SCHLIEEN_WARTEN" | "SCHLIESSEN_WARTEN" | "CERRAR_ESPERA" => "CLOSE_WAIT",
+        "SYN_GESENDET" | "SYN_ENVOY" | "SYN_ENVIADO" => "SYN_SENT",
+        "SYN_RECV" | "SYN_EMPFANGEN" | "SYN_REU" | "SYN_RECIBIDO" => "SYN_RECEIVED",
+        "GESCHLOSSEN" | "FERM" | "CERRADO" => "CLOSED",
+        _ => return upper,
+    };
+    normalized.to_string()
+}
+
+/// 已知输出宽度时地址列的最大宽度
+const MAX_ADDRESS_WIDTH: usize = 30;
+/// 已知输出宽度时进程名列的最大宽度
+const MAX_PROCESS_WIDTH: usize = 20;
+/// 边查询边输出时用户列的宽度
+const MAX_USER_WIDTH: usize = 16;
+/// 截断时路径列至少保留的宽度
+const MIN_PATH_WIDTH: usize = 10;
+
+/// 连接表格的列宽布局
+///
+/// 各列宽度取表头和所有待显示行中的最大值，使表格紧凑且对齐；
+/// 已知输出宽度（终端或`--width`）时地址和进程名列不超过上限，超出部分被截断。
+struct Layout {
+    /// 输出的最大列数，为`None`时不截断
+    width: Option<usize>,
+    protocol: usize,
+    local: usize,
+    foreign: usize,
+    state: usize,
+    pid: usize,
+    user: usize,
+    process: usize,
+}
+
+impl Layout {
+    /// 根据待显示的连接计算列宽
+    fn measure<'a>(
+        rows: impl IntoIterator<Item = &'a Connection>,
+        args: &PortownArgs,
+        width: Option<usize>,
+    ) -> Self {
+        let mut layout = Layout {
+            width,
+            protocol: "PROTOCOL".len(),
+            local: "LOCAL ADDRESS".len(),
+            foreign: "FOREIGN ADDRESS".len(),
+            state: "STATE".len(),
+            pid: "PID".len(),
+            user: "USER".len(),
+            process: "PROCESS".len(),
         };
-        print_connection(
-            protocol,
-            local_address,
-            foreign_address,
-            state,
-            pid,
-            name,
-            path,
-            bg_color,
-        )?;
+
+        let len = |value: &str| value.chars().count();
+        for conn in rows {
+            layout.protocol = layout.protocol.max(len(&conn.protocol));
+            layout.local = layout.local.max(len(&local_label(conn, args)));
+            layout.foreign = layout.foreign.max(len(&conn.foreign_address));
+            layout.state = layout.state.max(len(&conn.state));
+            layout.pid = layout.pid.max(len(&conn.pid));
+            if let Some(user) = &conn.user {
+                layout.user = layout.user.max(len(user));
+            }
+            layout.process = layout.process.max(len(&conn.process_name));
+        }
+
+        if width.is_some() {
+            layout.local = layout.local.min(MAX_ADDRESS_WIDTH);
+            layout.foreign = layout.foreign.min(MAX_ADDRESS_WIDTH);
+            layout.process = layout.process.min(MAX_PROCESS_WIDTH);
+        }
+        layout
     }
 
-    // 记录命令执行时间
-    crate::utils::log_command_metrics("portown", start_time.elapsed().as_millis(), "success", None);
+    /// 计算边查询边输出时的列宽
+    ///
+    /// netstat提供的列按所有连接（包括`--limit`之外的连接）计算；
+    /// 进程名和用户列在查询前未知，使用固定宽度。
+    fn for_streaming(connections: &[Connection], args: &PortownArgs, width: Option<usize>) -> Self {
+        Layout {
+            user: MAX_USER_WIDTH,
+            process: MAX_PROCESS_WIDTH,
+            ..Layout::measure(connections, args, width)
+        }
+    }
 
-    Ok(())
+    /// 路径（或命令行）列之前所有列的总宽度，包括列间空格
+    fn leading_width(&self, args: &PortownArgs) -> usize {
+        let mut total =
+            self.protocol + self.local + self.foreign + self.state + self.pid + self.process + 6;
+        if args.user {
+            total += self.user + 1;
+        }
+        if args.uptime {
+            total += 11;
+        }
+        if args.stats {
+            total += 22;
+        }
+        total
+    }
+
+    /// 在已知输出宽度时将内容截断到列宽，否则原样返回
+    fn fit(&self, value: &str, max: usize) -> String {
+        match self.width {
+            Some(_) => crate::utils::truncate_ellipsis(value, max),
+            None => value.to_string(),
+        }
+    }
 }
 
-fn print_header() -> io::Result<()> {
-    let mut stdout = StandardStream::stdout(ColorChoice::Always);
+/// 本地地址列的内容，启用`--services`时附带服务名称
+fn local_label(conn: &Connection, args: &PortownArgs) -> String {
+    let service = if args.services {
+        parse_port(&conn.local_address)
+            .and_then(|port| crate::utils::service_name(port, &conn.protocol))
+    } else {
+        None
+    };
+    match service {
+        Some(name) => format!("{} ({})", conn.local_address, name),
+        None => conn.local_address.clone(),
+    }
+}
+
+/// 将主题颜色转换为termcolor的前景色，`none`表示使用终端默认颜色
+fn themed(color: ThemeColor) -> Option<Color> {
+    color.0.map(crate::utils::termcolor_color)
+}
+
+/// 隔行背景色，指定`--no-stripes`或主题中`stripe = "none"`时为`None`
+///
+/// 关闭颜色（`--color never`或输出不是终端）时termcolor不输出任何颜色，背景色同样不会出现。
+fn stripe_color(args: &PortownArgs, theme: &Theme) -> Option<Color> {
+    if args.no_stripes {
+        None
+    } else {
+        themed(theme.stripe)
+    }
+}
 
+fn print_header(args: &PortownArgs, layout: &Layout, out: &mut dyn WriteColor) -> io::Result<()> {
     // 设置表头颜色
-    stdout.set_color(ColorSpec::new().set_fg(Some(Color::White)).set_bold(true))?;
+    out.set_color(ColorSpec::new().set_fg(Some(Color::White)).set_bold(true))?;
 
     // 打印表头
+    write!(
+        out,
+        "\n{:<protocol$} {:<local$} {:<foreign$} {:<state$} {:<pid$} ",
+        "PROTOCOL",
+        "LOCAL ADDRESS",
+        "FOREIGN ADDRESS",
+        "STATE",
+        "PID",
+        protocol = layout.protocol,
+        local = layout.local,
+        foreign = layout.foreign,
+        state = layout.state,
+        pid = layout.pid,
+    )?;
+    if args.user {
+        write!(out, "{:<width$} ", "USER", width = layout.user)?;
+    }
+    if args.uptime {
+        write!(out, "{:<10} ", "UPTIME")?;
+    }
+    if args.stats {
+        write!(out, "{:<10} {:<10} ", "MEM", "CPU")?;
+    }
     writeln!(
-        &mut stdout,
-        "\n{:<10} {:<25} {:<25} {:<15} {:<8} {:<20} {}",
-        "PROTOCOL", "LOCAL ADDRESS", "FOREIGN ADDRESS", "STATE", "PID", "PROCESS", "PATH"
+        out,
+        "{:<width$} {}",
+        "PROCESS",
+        if args.cmdline { "COMMAND" } else { "PATH" },
+        width = layout.process,
+    )?;
+
+    // 重置颜色
+    out.reset()?;
+
+    // 打印分隔线，宽度与表格一致（路径列按表头计）
+    let rule = layout.leading_width(args) + 7;
+    let rule = layout.width.map_or(rule, |w| rule.min(w));
+    writeln!(out, "{}", "─".repeat(rule))?;
+
+    Ok(())
+}
+
+/// `--group-by-state`的分组：返回排序用的序号和分组标题
+///
+/// LISTENING排在最前，其次是ESTABLISHED，其余状态（包括UDP的`-`）在最后
+fn state_group(conn: &Connection) -> (u8, &'static str) {
+    match conn.state.as_str() {
+        "LISTENING" => (0, "LISTENING"),
+        "ESTABLISHED" => (1, "ESTABLISHED"),
+        _ => (2, "OTHER"),
+    }
+}
+
+/// 输出`--group-by-state`的分组标题行（暗色）
+fn print_state_separator(label: &str, out: &mut dyn WriteColor) -> io::Result<()> {
+    out.set_color(ColorSpec::new().set_dimmed(true))?;
+    writeln!(out, "── {} ──", label)?;
+    out.reset()?;
+
+    Ok(())
+}
+
+fn print_footer(shown: usize, total: usize, out: &mut dyn WriteColor) -> io::Result<()> {
+    out.set_color(ColorSpec::new().set_dimmed(true))?;
+    writeln!(out, "\nShowing {} of {} connections", shown, total)?;
+    out.reset()?;
+
+    Ok(())
+}
+
+fn print_process_group(group: &ProcessGroup, out: &mut dyn WriteColor) -> io::Result<()> {
+    // 进程名
+    out.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)).set_bold(true))?;
+    write!(out, "{}", group.name)?;
+
+    // PID
+    out.set_color(ColorSpec::new().set_fg(Some(Color::White)))?;
+    write!(out, " ({}) -> ", group.pid)?;
+
+    // 端口列表
+    let ports: Vec<String> = group.ports.iter().map(|p| p.to_string()).collect();
+    out.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)))?;
+    writeln!(out, "[{}]", ports.join(", "))?;
+
+    // 重置颜色
+    out.reset()?;
+
+    Ok(())
+}
+
+fn print_conflicts(
+    conflicts: &[PortConflict],
+    args: &PortownArgs,
+    theme: &Theme,
+    width: Option<usize>,
+    out: &mut dyn WriteColor,
+) -> crate::error::Result<()> {
+    if conflicts.is_empty() {
+        writeln!(out, "No port conflicts detected")?;
+        return Ok(());
+    }
+
+    let layout = Layout::measure(
+        conflicts.iter().flat_map(|conflict| &conflict.connections),
+        args,
+        width,
+    );
+
+    for conflict in conflicts {
+        // 冲突标题
+        out.set_color(ColorSpec::new().set_fg(Some(Color::Red)).set_bold(true))?;
+        writeln!(
+            out,
+            "\n{} port {} is bound by {} connections",
+            conflict.protocol,
+            conflict.port,
+            conflict.connections.len()
+        )?;
+        out.reset()?;
+
+        for conn in &conflict.connections {
+            print_connection(conn, 0, args, theme, None, &layout, out)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn print_connection(
+    conn: &Connection,
+    more: usize,
+    args: &PortownArgs,
+    theme: &Theme,
+    bg_color: Option<Color>,
+    layout: &Layout,
+    out: &mut dyn WriteColor,
+) -> io::Result<()> {
+    // 设置背景色（如果有）
+    if let Some(color) = bg_color {
+        out.set_color(ColorSpec::new().set_bg(Some(color)))?;
+    }
+
+    // 协议颜色
+    let protocol_color = match conn.protocol.as_str() {
+        "TCP" => themed(theme.tcp),
+        "UDP" => themed(theme.udp),
+        _ => Some(Color::White),
+    };
+    out.set_color(
+        ColorSpec::new()
+            .set_fg(protocol_color)
+            .set_bold(true)
+            .set_bg(bg_color),
+    )?;
+    write!(out, "{:<width$} ", conn.protocol, width = layout.protocol)?;
+
+    // 本地地址
+    out.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)).set_bg(bg_color))?;
+    write!(
+        out,
+        "{:<width$} ",
+        layout.fit(&local_label(conn, args), layout.local),
+        width = layout.local
+    )?;
+
+    // 远程地址
+    out.set_color(ColorSpec::new().set_fg(Some(Color::Blue)).set_bg(bg_color))?;
+    write!(
+        out,
+        "{:<width$} ",
+        layout.fit(&conn.foreign_address, layout.foreign),
+        width = layout.foreign
     )?;
 
-    // 重置颜色
-    stdout.reset()?;
+    // 状态
+    let state_color = match conn.state.as_str() {
+        "LISTENING" => themed(theme.listening),
+        "ESTABLISHED" => themed(theme.established),
+        "CLOSE_WAIT" => Some(Color::Red),
+        "TIME_WAIT" => Some(Color::Magenta),
+        _ => Some(Color::White),
+    };
+    out.set_color(ColorSpec::new().set_fg(state_color).set_bg(bg_color))?;
+    write!(out, "{:<width$} ", conn.state, width = layout.state)?;
+
+    // PID
+    out.set_color(ColorSpec::new().set_fg(Some(Color::White)).set_bg(bg_color))?;
+    write!(out, "{:<width$} ", conn.pid, width = layout.pid)?;
+
+    // 所属用户
+    if let Some(user) = &conn.user {
+        out.set_color(
+            ColorSpec::new()
+                .set_fg(Some(Color::Magenta))
+                .set_bg(bg_color),
+        )?;
+        write!(
+            out,
+            "{:<width$} ",
+            layout.fit(user, layout.user),
+            width = layout.user
+        )?;
+    }
+
+    // 进程运行时长
+    if let Some(uptime) = &conn.uptime {
+        out.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)).set_bg(bg_color))?;
+        write!(out, "{:<10} ", uptime)?;
+    }
+
+    // 进程内存和CPU时间
+    if let (Some(memory), Some(cpu_time)) = (&conn.memory, &conn.cpu_time) {
+        out.set_color(ColorSpec::new().set_fg(Some(Color::Green)).set_bg(bg_color))?;
+        write!(out, "{:<10} {:<10} ", memory, cpu_time)?;
+    }
+
+    // 进程名
+    out.set_color(
+        ColorSpec::new()
+            .set_fg(Some(Color::Yellow))
+            .set_bg(bg_color),
+    )?;
+    write!(
+        out,
+        "{:<width$} ",
+        layout.fit(&conn.process_name, layout.process),
+        width = layout.process
+    )?;
+
+    // 进程路径（启用--cmdline时为命令行），用剩余宽度显示
+    out.set_color(ColorSpec::new().set_fg(Some(Color::White)).set_bg(bg_color))?;
+    let path = conn.command_line.as_deref().unwrap_or(&conn.process_path);
+    let suffix = if more > 0 {
+        format!(" (+{} more)", more)
+    } else {
+        String::new()
+    };
+    let path_width = layout
+        .width
+        .map_or(0, |w| {
+            w.saturating_sub(layout.leading_width(args) + suffix.chars().count())
+        })
+        .max(MIN_PATH_WIDTH);
+    writeln!(out, "{}{}", layout.fit(path, path_width), suffix)?;
+
+    // 重置颜色
+    out.reset()?;
+
+    Ok(())
+}
+
+/// 获取一组进程的命令行，无法获取的PID不出现在结果中
+///
+/// Windows上调用一次`wmic`批量查询所有进程，避免逐个PID启动子进程
+#[cfg(windows)]
+fn get_command_lines(pids: &HashSet<String>) -> HashMap<String, String> {
+    let output = match Command::new("wmic")
+        .args(["process", "get", "ProcessId,CommandLine", "/format:csv"])
+        .output()
+    {
+        Ok(output) if output.status.success() => output,
+        _ => return HashMap::new(),
+    };
+
+    // CSV列为 Node,CommandLine,ProcessId，命令行本身可能包含逗号
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (rest, pid) = line.trim_end().rsplit_once(',')?;
+            let (_, command_line) = rest.split_once(',')?;
+            if command_line.is_empty() || !pids.contains(pid) {
+                return None;
+            }
+            Some((pid.to_string(), command_line.to_string()))
+        })
+        .collect()
+}
+
+/// 获取一组进程的命令行，无法获取的PID不出现在结果中
+///
+/// Linux上读取`/proc/<pid>/cmdline`，参数之间以空格连接
+#[cfg(not(windows))]
+fn get_command_lines(pids: &HashSet<String>) -> HashMap<String, String> {
+    pids.iter()
+        .filter_map(|pid| {
+            let raw = std::fs::read(format!("/proc/{}/cmdline", pid)).ok()?;
+            let args: Vec<String> = raw
+                .split(|b| *b == 0)
+                .filter(|arg| !arg.is_empty())
+                .map(|arg| String::from_utf8_lossy(arg).into_owned())
+                .collect();
+            if args.is_empty() {
+                return None;
+            }
+            Some((pid.clone(), args.join(" ")))
+        })
+        .collect()
+}
+
+/// 获取进程所属用户，格式为`域\用户名`
+///
+/// 依次调用`OpenProcessToken`、`GetTokenInformation(TokenUser)`和`LookupAccountSidW`
+#[cfg(windows)]
+fn get_process_user(pid: &str) -> Option<String> {
+    use winapi::shared::minwindef::{DWORD, FALSE};
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::processthreadsapi::{OpenProcess, OpenProcessToken};
+    use winapi::um::securitybaseapi::GetTokenInformation;
+    use winapi::um::winbase::LookupAccountSidW;
+    use winapi::um::winnt::{
+        TokenUser, HANDLE, PROCESS_QUERY_LIMITED_INFORMATION, SID_NAME_USE, TOKEN_QUERY, TOKEN_USER,
+    };
+
+    let pid_num: DWORD = pid.parse().ok()?;
+
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, FALSE, pid_num);
+        if process.is_null() {
+            return None;
+        }
+
+        let mut token: HANDLE = std::ptr::null_mut();
+        let opened = OpenProcessToken(process, TOKEN_QUERY, &mut token) != 0;
+        CloseHandle(process);
+        if !opened {
+            return None;
+        }
+
+        // 使用u64缓冲区保证TOKEN_USER的对齐要求
+        let mut buffer = [0u64; 64];
+        let mut returned: DWORD = 0;
+        let queried = GetTokenInformation(
+            token,
+            TokenUser,
+            buffer.as_mut_ptr() as *mut _,
+            std::mem::size_of_val(&buffer) as DWORD,
+            &mut returned,
+        ) != 0;
+        CloseHandle(token);
+        if !queried {
+            return None;
+        }
+
+        let token_user = &*(buffer.as_ptr() as *const TOKEN_USER);
+        let mut name = [0u16; 256];
+        let mut name_len = name.len() as DWORD;
+        let mut domain = [0u16; 256];
+        let mut domain_len = domain.len() as DWORD;
+        let mut sid_type: SID_NAME_USE = 0;
+        if LookupAccountSidW(
+            std::ptr::null(),
+            token_user.User.Sid,
+            name.as_mut_ptr(),
+            &mut name_len,
+            domain.as_mut_ptr(),
+            &mut domain_len,
+            &mut sid_type,
+        ) == 0
+        {
+            return None;
+        }
+
+        let name = String::from_utf16_lossy(&name[..name_len as usize]);
+        let domain = String::from_utf16_lossy(&domain[..domain_len as usize]);
+        Some(if domain.is_empty() {
+            name
+        } else {
+            format!("{}\\{}", domain, name)
+        })
+    }
+}
+
+/// 获取进程所属用户名
+///
+/// 读取`/proc/<pid>/status`中的Uid，再从`/etc/passwd`查找用户名，
+/// 找不到用户名时返回数字UID
+#[cfg(not(windows))]
+fn get_process_user(pid: &str) -> Option<String> {
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    let uid = status
+        .lines()
+        .find_map(|line| line.strip_prefix("Uid:"))?
+        .split_whitespace()
+        .next()?;
+
+    let passwd = std::fs::read_to_string("/etc/passwd").unwrap_or_default();
+    let name = passwd.lines().find_map(|line| {
+        let mut fields = line.split(':');
+        let name = fields.next()?;
+        let _password = fields.next()?;
+        (fields.next()? == uid).then(|| name.to_string())
+    });
+
+    Some(name.unwrap_or_else(|| uid.to_string()))
+}
+
+/// 进程的资源占用
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ProcessStats {
+    /// 常驻内存字节数（Windows上为工作集大小）
+    resident_bytes: u64,
+    /// 用户态与内核态CPU时间之和
+    cpu_time: std::time::Duration,
+}
+
+/// 获取进程的内存和CPU时间
+///
+/// 调用`GetProcessMemoryInfo`读取工作集大小，`GetProcessTimes`读取CPU时间
+#[cfg(windows)]
+fn get_process_stats(pid: &str) -> Option<ProcessStats> {
+    use std::time::Duration;
+    use winapi::shared::minwindef::{DWORD, FALSE, FILETIME};
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::processthreadsapi::{GetProcessTimes, OpenProcess};
+    use winapi::um::psapi::{GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS};
+    use winapi::um::winnt::{PROCESS_QUERY_LIMITED_INFORMATION, PROCESS_VM_READ};
+
+    let pid_num: DWORD = pid.parse().ok()?;
+
+    unsafe {
+        let process = OpenProcess(
+            PROCESS_QUERY_LIMITED_INFORMATION | PROCESS_VM_READ,
+            FALSE,
+            pid_num,
+        );
+        if process.is_null() {
+            return None;
+        }
+
+        let mut counters: PROCESS_MEMORY_COUNTERS = std::mem::zeroed();
+        let memory_ok = GetProcessMemoryInfo(
+            process,
+            &mut counters,
+            std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as DWORD,
+        ) != 0;
+
+        let empty = || FILETIME {
+            dwLowDateTime: 0,
+            dwHighDateTime: 0,
+        };
+        let (mut creation, mut exit, mut kernel, mut user) = (empty(), empty(), empty(), empty());
+        let times_ok =
+            GetProcessTimes(process, &mut creation, &mut exit, &mut kernel, &mut user) != 0;
+        CloseHandle(process);
+        if !memory_ok || !times_ok {
+            return None;
+        }
+
+        // FILETIME时长单位为100纳秒
+        let intervals = |t: &FILETIME| ((t.dwHighDateTime as u64) << 32) | t.dwLowDateTime as u64;
+        Some(ProcessStats {
+            resident_bytes: counters.WorkingSetSize as u64,
+            cpu_time: Duration::from_nanos((intervals(&kernel) + intervals(&user)) * 100),
+        })
+    }
+}
+
+/// 获取进程的内存和CPU时间
+///
+/// 读取`/proc/<pid>/statm`第2个字段（常驻页数）和`/proc/<pid>/stat`中的
+/// utime、stime字段。页大小按4KiB、时钟频率按100Hz计算。
+#[cfg(not(windows))]
+fn get_process_stats(pid: &str) -> Option<ProcessStats> {
+    use std::time::Duration;
+
+    const PAGE_SIZE: u64 = 4096;
+    const CLOCK_TICKS_PER_SEC: u64 = 100;
+
+    let statm = std::fs::read_to_string(format!("/proc/{}/statm", pid)).ok()?;
+    let resident_pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // utime、stime分别为第14、15个字段，')'之后从第3个字段开始
+    let mut fields = stat.rsplit_once(')')?.1.split_whitespace().skip(11);
+    let utime: u64 = fields.next()?.parse().ok()?;
+    let stime: u64 = fields.next()?.parse().ok()?;
+
+    Some(ProcessStats {
+        resident_bytes: resident_pages * PAGE_SIZE,
+        cpu_time: Duration::from_millis((utime + stime) * 1000 / CLOCK_TICKS_PER_SEC),
+    })
+}
+
+/// 获取进程的启动时间
+///
+/// 调用`GetProcessTimes`读取创建时间（自1601-01-01起的100纳秒间隔数）
+#[cfg(windows)]
+fn get_process_start_time(pid: &str) -> Option<std::time::SystemTime> {
+    use std::time::{Duration, UNIX_EPOCH};
+    use winapi::shared::minwindef::{DWORD, FALSE, FILETIME};
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::processthreadsapi::{GetProcessTimes, OpenProcess};
+    use winapi::um::winnt::PROCESS_QUERY_LIMITED_INFORMATION;
+
+    // 1601-01-01到1970-01-01之间的秒数
+    const WINDOWS_TO_UNIX_SECS: u64 = 11_644_473_600;
 
-    // 打印分隔线
-    writeln!(&mut stdout, "{}", "─".repeat(120))?;
+    let pid_num: DWORD = pid.parse().ok()?;
 
-    Ok(())
-}
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, FALSE, pid_num);
+        if process.is_null() {
+            return None;
+        }
 
-fn print_connection(
-    protocol: &str,
-    local_addr: &str,
-    foreign_addr: &str,
-    state: &str,
-    pid: &str,
-    proc_name: &str,
-    proc_path: &str,
-    bg_color: Option<Color>,
-) -> io::Result<()> {
-    let mut stdout = StandardStream::stdout(ColorChoice::Always);
+        let empty = || FILETIME {
+            dwLowDateTime: 0,
+            dwHighDateTime: 0,
+        };
+        let (mut creation, mut exit, mut kernel, mut user) = (empty(), empty(), empty(), empty());
+        let queried =
+            GetProcessTimes(process, &mut creation, &mut exit, &mut kernel, &mut user) != 0;
+        CloseHandle(process);
+        if !queried {
+            return None;
+        }
 
-    // 设置背景色（如果有）
-    if let Some(color) = bg_color {
-        stdout.set_color(ColorSpec::new().set_bg(Some(color)))?;
+        let intervals = ((creation.dwHighDateTime as u64) << 32) | creation.dwLowDateTime as u64;
+        let since_unix = (intervals / 10_000_000).checked_sub(WINDOWS_TO_UNIX_SECS)?;
+        Some(UNIX_EPOCH + Duration::from_secs(since_unix))
     }
+}
 
-    // 协议颜色
-    stdout.set_color(
-        ColorSpec::new()
-            .set_fg(Some(match protocol {
-                "TCP" => Color::Green,
-                "UDP" => Color::Yellow,
-                _ => Color::White,
-            }))
-            .set_bold(true)
-            .set_bg(bg_color),
-    )?;
-    write!(&mut stdout, "{:<10} ", protocol)?;
+/// 获取进程的启动时间
+///
+/// 读取`/proc/<pid>/stat`第22个字段（自开机起的时钟滴答数），
+/// 加上`/proc/stat`中的开机时间`btime`。时钟频率按Linux默认的100Hz计算。
+#[cfg(not(windows))]
+fn get_process_start_time(pid: &str) -> Option<std::time::SystemTime> {
+    use std::time::{Duration, UNIX_EPOCH};
 
-    // 本地地址
-    stdout.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)).set_bg(bg_color))?;
-    write!(&mut stdout, "{:<25} ", local_addr)?;
+    const CLOCK_TICKS_PER_SEC: u64 = 100;
 
-    // 远程地址
-    stdout.set_color(ColorSpec::new().set_fg(Some(Color::Blue)).set_bg(bg_color))?;
-    write!(&mut stdout, "{:<25} ", foreign_addr)?;
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // 进程名可能包含空格和括号，从最后一个')'之后开始按字段解析（第3个字段起）
+    let start_ticks: u64 = stat
+        .rsplit_once(')')?
+        .1
+        .split_whitespace()
+        .nth(19)?
+        .parse()
+        .ok()?;
 
-    // 状态
-    let state_color = match state {
-        "LISTENING" => Color::Yellow,
-        "ESTABLISHED" => Color::Green,
-        "CLOSE_WAIT" => Color::Red,
-        "TIME_WAIT" => Color::Magenta,
-        _ => Color::White,
-    };
-    stdout.set_color(ColorSpec::new().set_fg(Some(state_color)).set_bg(bg_color))?;
-    write!(&mut stdout, "{:<15} ", state)?;
+    let boot_time: u64 = std::fs::read_to_string("/proc/stat")
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("btime "))?
+        .trim()
+        .parse()
+        .ok()?;
 
-    // PID
-    stdout.set_color(ColorSpec::new().set_fg(Some(Color::White)).set_bg(bg_color))?;
-    write!(&mut stdout, "{:<8} ", pid)?;
+    Some(UNIX_EPOCH + Duration::from_secs(boot_time + start_ticks / CLOCK_TICKS_PER_SEC))
+}
 
-    // 进程名
-    stdout.set_color(
-        ColorSpec::new()
-            .set_fg(Some(Color::Yellow))
-            .set_bg(bg_color),
-    )?;
-    write!(&mut stdout, "{:<20} ", proc_name)?;
+/// 获取所有进程的父进程ID，键和值均为PID字符串
+///
+/// 通过`CreateToolhelp32Snapshot`一次获取全部进程的`th32ParentProcessID`
+#[cfg(windows)]
+fn get_parent_pids() -> HashMap<String, String> {
+    use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+    use winapi::um::tlhelp32::{
+        CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W,
+        TH32CS_SNAPPROCESS,
+    };
+
+    let mut parents = HashMap::new();
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0);
+        if snapshot == INVALID_HANDLE_VALUE {
+            return parents;
+        }
 
-    // 进程路径
-    stdout.set_color(ColorSpec::new().set_fg(Some(Color::White)).set_bg(bg_color))?;
-    writeln!(&mut stdout, "{}", proc_path)?;
+        let mut entry: PROCESSENTRY32W = std::mem::zeroed();
+        entry.dwSize = std::mem::size_of::<PROCESSENTRY32W>() as u32;
+        let mut found = Process32FirstW(snapshot, &mut entry) != 0;
+        while found {
+            parents.insert(
+                entry.th32ProcessID.to_string(),
+                entry.th32ParentProcessID.to_string(),
+            );
+            found = Process32NextW(snapshot, &mut entry) != 0;
+        }
+        CloseHandle(snapshot);
+    }
+    parents
+}
 
-    // 重置颜色
-    stdout.reset()?;
+/// 获取所有进程的父进程ID，键和值均为PID字符串
+///
+/// 读取每个`/proc/<pid>/stat`的第4个字段（ppid），没有`/proc`的平台返回空映射
+#[cfg(not(windows))]
+fn get_parent_pids() -> HashMap<String, String> {
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return HashMap::new();
+    };
 
-    Ok(())
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let pid = entry.file_name().into_string().ok()?;
+            if !pid.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            let stat = std::fs::read_to_string(entry.path().join("stat")).ok()?;
+            // 进程名可能包含空格和括号，从最后一个')'之后开始解析
+            let ppid = stat.rsplit_once(')')?.1.split_whitespace().nth(1)?;
+            Some((pid, ppid.to_string()))
+        })
+        .collect()
 }
 
 lazy_static::lazy_static! {
     static ref PROCESS_CACHE: Mutex<HashMap<String, (String, String)>> = Mutex::new(HashMap::new());
 }
 
+/// 将Win32 API（`W`版本）填充的UTF-16缓冲区转换为字符串
+///
+/// 只取API返回的前`length`个字符，并在遇到NUL时截止，
+/// 不会把缓冲区剩余的填充字符带入结果。按UTF-16解码，
+/// 非ASCII路径（如中文系统的`C:\用户`）不受系统ANSI代码页影响。
+#[cfg(windows)]
+fn wide_buffer_to_string(buffer: &[u16], length: usize) -> String {
+    let written = &buffer[..length.min(buffer.len())];
+    let end = written
+        .iter()
+        .position(|&unit| unit == 0)
+        .unwrap_or(written.len());
+    String::from_utf16_lossy(&written[..end])
+}
+
+/// 无法打开句柄的Windows系统进程，按PID给出固定的显示名称
+#[cfg(windows)]
+const SYSTEM_PROCESSES: &[(&str, &str)] = &[("0", "System Idle Process"), ("4", "System")];
+
+/// 查找系统保留PID对应的进程名
+#[cfg(windows)]
+fn system_process_name(pid: &str) -> Option<&'static str> {
+    SYSTEM_PROCESSES
+        .iter()
+        .find(|(system_pid, _)| *system_pid == pid)
+        .map(|(_, name)| *name)
+}
+
+/// 获取进程名和可执行文件路径，结果按PID缓存
+///
+/// 系统保留PID（0、4）直接使用固定名称；`OpenProcess`以
+/// `PROCESS_QUERY_LIMITED_INFORMATION`失败时再尝试`PROCESS_QUERY_INFORMATION`。
+/// 依次调用`OpenProcess`、`QueryFullProcessImageNameW`和`GetModuleFileNameExW`
+#[cfg(windows)]
 fn get_process_info(pid: &str) -> crate::error::Result<(String, String)> {
     use std::time::Instant;
     use winapi::shared::minwindef::{DWORD, FALSE};
     use winapi::shared::ntdef::HANDLE;
     use winapi::um::handleapi::CloseHandle;
     use winapi::um::processthreadsapi::OpenProcess;
-    use winapi::um::psapi::GetModuleFileNameExA;
-    use winapi::um::winbase::QueryFullProcessImageNameA;
-    use winapi::um::winnt::PROCESS_QUERY_LIMITED_INFORMATION;
+    use winapi::um::psapi::GetModuleFileNameExW;
+    use winapi::um::winbase::QueryFullProcessImageNameW;
+    use winapi::um::winnt::{PROCESS_QUERY_INFORMATION, PROCESS_QUERY_LIMITED_INFORMATION};
 
     let start_time = Instant::now();
 
@@ -289,6 +1998,14 @@ fn get_process_info(pid: &str) -> crate::error::Result<(String, String)> {
         }
     }
 
+    // 系统进程无法打开句柄，也没有可执行文件路径
+    if let Some(name) = system_process_name(pid) {
+        let info = (name.to_string(), "-".to_string());
+        let mut cache = PROCESS_CACHE.lock().unwrap();
+        cache.insert(pid.to_string(), info.clone());
+        return Ok(info);
+    }
+
     // 使用Windows API获取进程信息
     let pid_num: DWORD = pid.parse().unwrap_or(0);
     let process_handle: HANDLE;
@@ -334,16 +2051,15 @@ fn get_process_info(pid: &str) -> crate::error::Result<(String, String)> {
             winapi::um::handleapi::CloseHandle(token);
         }
 
-        process_handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, FALSE, pid_num);
+        let mut handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, FALSE, pid_num);
+        if handle.is_null() {
+            // 部分受保护进程只拒绝受限访问掩码，换用完整查询权限再试一次
+            handle = OpenProcess(PROCESS_QUERY_INFORMATION, FALSE, pid_num);
+        }
+        process_handle = handle;
         if process_handle.is_null() {
-            let _last_error = winapi::um::errhandlingapi::GetLastError();
-            /*
-            eprintln!(
-                "Failed to open process with PID: {} (Error: {})",
-                pid,
-                last_error
-            );
-            */
+            let last_error = winapi::um::errhandlingapi::GetLastError();
+            log::warn!("failed to open process {} (error {})", pid, last_error);
             // 即使失败也更新缓存，避免重复尝试
             let mut cache = PROCESS_CACHE.lock().unwrap();
             cache.insert(
@@ -355,44 +2071,43 @@ fn get_process_info(pid: &str) -> crate::error::Result<(String, String)> {
     }
 
     // 获取进程名
-    let mut name_buffer = [0u8; 260];
+    let mut name_buffer = [0u16; 260];
     let mut name = "Unknown".to_string();
 
     unsafe {
         let mut size = name_buffer.len() as DWORD;
-        if QueryFullProcessImageNameA(
-            process_handle,
-            0,
-            name_buffer.as_mut_ptr() as *mut i8,
-            &mut size,
-        ) != 0
-        {
-            name = String::from_utf8_lossy(&name_buffer[..size as usize]).to_string();
+        if QueryFullProcessImageNameW(process_handle, 0, name_buffer.as_mut_ptr(), &mut size) != 0 {
+            name = wide_buffer_to_string(&name_buffer, size as usize);
             if let Some(last_slash) = name.rfind('\\') {
                 name = name[last_slash + 1..].to_string();
             }
-            //eprintln!("Successfully got process name for PID {}: {}", pid, name);
+            log::debug!("process {} name: {}", pid, name);
         } else {
-            //eprintln!("Failed to get process name for PID: {}", pid);
+            log::debug!("failed to get process name for {}", pid);
         }
     }
 
     // 获取进程路径
     let mut path = "Unknown".to_string();
     unsafe {
-        let mut path_buffer = [0u8; 260];
-        if GetModuleFileNameExA(
+        let mut path_buffer = [0u16; 260];
+        // 返回值为写入的字符数（不含结尾的NUL），失败时为0
+        let length = GetModuleFileNameExW(
             process_handle,
             std::ptr::null_mut(),
-            path_buffer.as_mut_ptr() as *mut i8,
+            path_buffer.as_mut_ptr(),
             path_buffer.len() as DWORD,
-        ) != 0
-        {
-            path = String::from_utf8_lossy(&path_buffer).to_string();
-            //eprintln!("Successfully got process path for PID {}: {}", pid, path);
+        );
+        if length != 0 {
+            path = wide_buffer_to_string(&path_buffer, length as usize);
+            log::debug!("process {} path: {}", pid, path);
         } else {
-            let _last_error = winapi::um::errhandlingapi::GetLastError();
-            //eprintln!("Failed to get process path for PID: {} (Error: {})", pid, last_error);
+            let last_error = winapi::um::errhandlingapi::GetLastError();
+            log::debug!(
+                "failed to get process path for {} (error {})",
+                pid,
+                last_error
+            );
         }
         CloseHandle(process_handle);
     }
@@ -413,3 +2128,1050 @@ fn get_process_info(pid: &str) -> crate::error::Result<(String, String)> {
 
     Ok((name, path))
 }
+
+/// 获取进程名和可执行文件路径，结果按PID缓存
+///
+/// 读取`/proc/<pid>/comm`和`/proc/<pid>/exe`链接，无法读取时为"Unknown"
+#[cfg(not(windows))]
+fn get_process_info(pid: &str) -> crate::error::Result<(String, String)> {
+    if let Some(info) = PROCESS_CACHE.lock().unwrap().get(pid) {
+        return Ok(info.clone());
+    }
+
+    // netstat无法获取PID时显示为`-`，无需查询
+    if pid.parse::<u32>().is_err() {
+        return Ok(("Unknown".to_string(), "Unknown".to_string()));
+    }
+
+    let name = std::fs::read_to_string(format!("/proc/{}/comm", pid))
+        .map(|name| name.trim_end().to_string())
+        .unwrap_or_else(|err| {
+            log::warn!("failed to read name of process {}: {}", pid, err);
+            "Unknown".to_string()
+        });
+    let path = std::fs::read_link(format!("/proc/{}/exe", pid))
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|err| {
+            log::debug!("failed to read path of process {}: {}", pid, err);
+            "Unknown".to_string()
+        });
+
+    PROCESS_CACHE
+        .lock()
+        .unwrap()
+        .insert(pid.to_string(), (name.clone(), path.clone()));
+    Ok((name, path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn args(flags: &[&str]) -> PortownArgs {
+        PortownArgs::try_parse_from(std::iter::once("portown").chain(flags.iter().copied()))
+            .unwrap()
+    }
+
+    /// 与`NoColor<Vec<u8>>`相同，但允许在写入过程中读取已输出的内容
+    #[derive(Clone, Default)]
+    struct SharedOutput(std::rc::Rc<std::cell::RefCell<Vec<u8>>>);
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedOutput {
+        fn lines(&self) -> usize {
+            self.0.borrow().iter().filter(|&&b| b == b'\n').count()
+        }
+
+        fn text(&self) -> String {
+            String::from_utf8(self.0.borrow().clone()).unwrap()
+        }
+    }
+
+    /// 返回固定netstat输出的连接来源
+    struct FakeSource(&'static str);
+
+    impl ConnectionSource for FakeSource {
+        fn netstat_output(&self) -> crate::error::Result<String> {
+            Ok(self.0.to_string())
+        }
+    }
+
+    /// 返回固定进程信息的查询：PID 10为nginx，11为sshd，其余未知
+    ///
+    /// 记录查询顺序；设置`watch`时同时记录每次查询前已输出的行数。
+    /// 其余字段用于所有进程，当前时间固定为[`fake_now`]。
+    #[derive(Default)]
+    struct FakeResolver {
+        command_line: Option<String>,
+        user: Option<String>,
+        start_time: Option<SystemTime>,
+        stats: Option<ProcessStats>,
+        resolved: Vec<String>,
+        watch: Option<SharedOutput>,
+        lines_before_resolve: Vec<usize>,
+    }
+
+    fn fake_now() -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+    }
+
+    impl ProcessResolver for FakeResolver {
+        fn now(&self) -> SystemTime {
+            fake_now()
+        }
+
+        fn name_and_path(&mut self, pid: &str) -> Option<(String, String)> {
+            self.resolved.push(pid.to_string());
+            if let Some(output) = &self.watch {
+                self.lines_before_resolve.push(output.lines());
+            }
+            let name = match pid {
+                "10" => "nginx",
+                "11" => "sshd",
+                _ => return None,
+            };
+            Some((name.to_string(), format!("/usr/bin/{}", name)))
+        }
+
+        fn command_line(&mut self, _pid: &str) -> Option<String> {
+            self.command_line.clone()
+        }
+
+        fn user(&mut self, _pid: &str) -> Option<String> {
+            self.user.clone()
+        }
+
+        fn start_time(&mut self, _pid: &str) -> Option<SystemTime> {
+            self.start_time
+        }
+
+        fn stats(&mut self, _pid: &str) -> Option<ProcessStats> {
+            self.stats
+        }
+    }
+
+    #[test]
+    fn resolve_connections_reads_the_source_and_resolves_each_pid_once() {
+        let source = FakeSource(
+            "Proto Local Address Foreign Address State PID\n\
+             TCP 0.0.0.0:80 0.0.0.0:0 LISTENING 10\n\
+             UDP 0.0.0.0:53 *:* 11\n\
+             TCP 10.0.0.1:80 10.0.0.2:5000 ESTABLISHED 10\n",
+        );
+        let mut resolver = FakeResolver::default();
+        let connections = resolve_connections(&source, &mut resolver, &args(&[])).unwrap();
+
+        assert_eq!(resolver.resolved, ["10", "11"]);
+        let rows: Vec<(&str, &str, &str, &str)> = connections
+            .iter()
+            .map(|c| {
+                (
+                    c.protocol.as_str(),
+                    c.local_address.as_str(),
+                    c.state.as_str(),
+                    c.process_name.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                ("TCP", "0.0.0.0:80", "LISTENING", "nginx"),
+                ("UDP", "0.0.0.0:53", "-", "sshd"),
+                ("TCP", "10.0.0.1:80", "ESTABLISHED", "nginx"),
+            ]
+        );
+        assert_eq!(connections[2].foreign_address, "10.0.0.2:5000");
+        assert_eq!(connections[2].process_path, "/usr/bin/nginx");
+
+        // 过滤条件在查询进程之前生效
+        let mut resolver = FakeResolver::default();
+        let udp = resolve_connections(&source, &mut resolver, &args(&["--udp"])).unwrap();
+        assert_eq!(udp.len(), 1);
+        assert_eq!(resolver.resolved, ["11"]);
+    }
+
+    /// 去掉列宽填充后逐行比较
+    fn normalized(output: &str) -> Vec<String> {
+        output
+            .lines()
+            .filter(|line| !line.starts_with('─'))
+            .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect()
+    }
+
+    #[test]
+    fn stream_table_prints_each_row_once_its_process_resolves() {
+        let connections: Vec<Connection> = [
+            "TCP 0.0.0.0:80 0.0.0.0:0 LISTENING 10",
+            "TCP 0.0.0.0:9000 0.0.0.0:0 LISTENING 99",
+            "TCP 0.0.0.0:22 0.0.0.0:0 LISTENING 11",
+            "TCP 10.0.0.1:80 10.0.0.2:5000 ESTABLISHED 10",
+        ]
+        .iter()
+        .map(|line| parse_line(line).unwrap())
+        .collect();
+
+        let output = SharedOutput::default();
+        let mut out = NoColor::new(output.clone());
+        let mut resolver = FakeResolver {
+            watch: Some(output.clone()),
+            ..FakeResolver::default()
+        };
+        let streamed = stream_table(
+            connections.clone(),
+            &mut resolver,
+            &args(&[]),
+            &Theme::default(),
+            Some(200),
+            &mut out,
+        )
+        .unwrap();
+
+        // 每个PID只查询一次，且查询下一个PID之前已输出上一行
+        assert_eq!(resolver.resolved, ["10", "99", "11"]);
+        assert_eq!(resolver.lines_before_resolve, [3, 4, 4]);
+
+        let mut expected = connections;
+        fill_process_info(&mut expected, &mut FakeResolver::default(), &args(&[]));
+        assert_eq!(streamed, expected);
+
+        // 与一次性渲染逐字节相同：已知进程在前，未知进程在后
+        let mut rendered = NoColor::new(Vec::new());
+        render(
+            &expected,
+            &args(&[]),
+            &Theme::default(),
+            Some(200),
+            &mut rendered,
+        )
+        .unwrap();
+        assert_eq!(
+            output.text(),
+            String::from_utf8(rendered.into_inner()).unwrap()
+        );
+        assert_eq!(
+            normalized(&output.text()).last().unwrap(),
+            "TCP 0.0.0.0:9000 0.0.0.0:0 LISTENING 99 Unknown Unknown"
+        );
+    }
+
+    #[test]
+    fn stream_table_honors_limit() {
+        let connections: Vec<Connection> = [
+            "TCP 0.0.0.0:9000 0.0.0.0:0 LISTENING 99",
+            "TCP 0.0.0.0:80 0.0.0.0:0 LISTENING 10",
+            "TCP 0.0.0.0:22 0.0.0.0:0 LISTENING 11",
+        ]
+        .iter()
+        .map(|line| parse_line(line).unwrap())
+        .collect();
+        let mut out = NoColor::new(Vec::new());
+        stream_table(
+            connections,
+            &mut FakeResolver::default(),
+            &args(&["--limit", "2"]),
+            &Theme::default(),
+            Some(200),
+            &mut out,
+        )
+        .unwrap();
+        let text = String::from_utf8(out.into_inner()).unwrap();
+        assert!(text.contains("nginx") && text.contains("sshd"));
+        assert!(!text.contains(":9000"));
+        assert!(text.contains("Showing 2 of 3 connections"));
+    }
+
+    #[test]
+    fn streaming_is_limited_to_the_plain_table() {
+        assert!(streams_rows(&args(&["-l", "--limit", "5"])));
+        assert!(!streams_rows(&args(&["--dedup-process"])));
+        assert!(!streams_rows(&args(&["--format", "json"])));
+        assert!(!streams_rows(&args(&["--group-by-state"])));
+    }
+
+    #[test]
+    fn cmdline_replaces_the_path_with_the_resolved_command_line() {
+        let source = FakeSource("TCP 0.0.0.0:80 0.0.0.0:0 LISTENING 10\n");
+        let args = args(&["--cmdline"]);
+        let mut resolver = FakeResolver {
+            command_line: Some("nginx -g daemon off;".to_string()),
+            ..FakeResolver::default()
+        };
+        let connections = resolve_connections(&source, &mut resolver, &args).unwrap();
+        assert_eq!(
+            connections[0].command_line.as_deref(),
+            Some("nginx -g daemon off;")
+        );
+
+        let mut out = NoColor::new(Vec::new());
+        render(&connections, &args, &Theme::default(), None, &mut out).unwrap();
+        let text = String::from_utf8(out.into_inner()).unwrap();
+        assert!(text.contains(" nginx -g daemon off;\n"), "{}", text);
+        assert!(!text.contains("/usr/bin/nginx"));
+    }
+
+    #[test]
+    fn user_column_shows_the_resolved_owner() {
+        let source = FakeSource(
+            "TCP 0.0.0.0:80 0.0.0.0:0 LISTENING 10\n\
+             TCP 0.0.0.0:22 0.0.0.0:0 LISTENING 11\n",
+        );
+        let args = args(&["--user"]);
+        let mut resolver = FakeResolver {
+            user: Some("www-data".to_string()),
+            ..FakeResolver::default()
+        };
+        let connections = resolve_connections(&source, &mut resolver, &args).unwrap();
+        assert!(connections
+            .iter()
+            .all(|c| c.user.as_deref() == Some("www-data")));
+
+        let mut out = NoColor::new(Vec::new());
+        render(&connections, &args, &Theme::default(), None, &mut out).unwrap();
+        let text = String::from_utf8(out.into_inner()).unwrap();
+        let rows = normalized(&text);
+        assert!(
+            rows.iter().any(|row| row.contains(" PID USER PROCESS ")),
+            "{}",
+            text
+        );
+        assert!(rows.contains(
+            &"TCP 0.0.0.0:80 0.0.0.0:0 LISTENING 10 www-data nginx /usr/bin/nginx".to_string()
+        ));
+    }
+
+    #[test]
+    fn stream_table_matches_render_byte_for_byte() {
+        let connections: Vec<Connection> = [
+            "TCP 0.0.0.0:9000 0.0.0.0:0 LISTENING 99",
+            "TCP 0.0.0.0:80 0.0.0.0:0 LISTENING 10",
+            "TCP [fe80::1234:5678:9abc:def0]:22 [fe80::1]:51000 ESTABLISHED 11",
+            "UDP 0.0.0.0:53 *:* 10",
+        ]
+        .iter()
+        .map(|line| parse_line(line).unwrap())
+        .collect();
+
+        for flags in [
+            &["--user"][..],
+            &["--limit", "2"],
+            &["--services", "--no-stripes"],
+        ] {
+            let args = args(flags);
+            let mut streamed = termcolor::Buffer::ansi();
+            let mut resolver = FakeResolver {
+                user: args.user.then(|| "a-rather-long-user-name".to_string()),
+                ..FakeResolver::default()
+            };
+            let resolved = stream_table(
+                connections.clone(),
+                &mut resolver,
+                &args,
+                &Theme::default(),
+                Some(120),
+                &mut streamed,
+            )
+            .unwrap();
+
+            let mut rendered = termcolor::Buffer::ansi();
+            render(
+                &resolved,
+                &args,
+                &Theme::default(),
+                Some(120),
+                &mut rendered,
+            )
+            .unwrap();
+            assert_eq!(
+                String::from_utf8_lossy(streamed.as_slice()),
+                String::from_utf8_lossy(rendered.as_slice()),
+                "{:?}",
+                flags
+            );
+        }
+    }
+
+    fn conn(line: &str, process_name: &str) -> Connection {
+        let mut conn = parse_line(line).unwrap();
+        conn.process_name = process_name.to_string();
+        conn.process_path = format!("/usr/bin/{}", process_name);
+        conn
+    }
+
+    #[test]
+    fn group_by_process_collects_ports_per_process() {
+        let connections = vec![
+            conn("TCP 0.0.0.0:80 0.0.0.0:0 LISTENING 10", "nginx"),
+            conn("TCP 0.0.0.0:443 0.0.0.0:0 LISTENING 10", "nginx"),
+            conn("TCP 10.0.0.2:5000 1.2.3.4:443 ESTABLISHED 12", "curl"),
+            conn("UDP 0.0.0.0:53 *:* 13", "dnsmasq"),
+        ];
+        let groups = group_by_process(&connections);
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups[0].ports, vec![80, 443]);
+    }
+
+    #[test]
+    fn find_conflicts_flags_ports_bound_by_several_processes() {
+        let connections = vec![
+            conn("TCP 0.0.0.0:80 0.0.0.0:0 LISTENING 10", "nginx"),
+            conn("TCP [::]:80 [::]:0 LISTENING 11", "apache"),
+            conn("TCP 10.0.0.2:5000 1.2.3.4:443 ESTABLISHED 12", "curl"),
+            conn("UDP 0.0.0.0:53 *:* 13", "dnsmasq"),
+        ];
+        let conflicts = find_conflicts(&connections);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].port, 80);
+        assert_eq!(conflicts[0].connections.len(), 2);
+    }
+
+    #[test]
+    fn parse_line_reads_windows_output() {
+        let conn = parse_line("  TCP    0.0.0.0:135    0.0.0.0:0    LISTENING    1044").unwrap();
+        assert_eq!(conn.protocol, "TCP");
+        assert_eq!(conn.local_address, "0.0.0.0:135");
+        assert_eq!(conn.foreign_address, "0.0.0.0:0");
+        assert_eq!(conn.state, "LISTENING");
+        assert_eq!(conn.pid, "1044");
+
+        let udp = parse_line("  UDP    0.0.0.0:5353    *:*    2280").unwrap();
+        assert_eq!(udp.state, "-");
+        assert_eq!(udp.pid, "2280");
+    }
+
+    #[test]
+    fn parse_line_normalizes_german_states() {
+        let listening = parse_line("  TCP    0.0.0.0:445    0.0.0.0:0    ABHÖREN    4").unwrap();
+        assert_eq!(listening.state, "LISTENING");
+
+        // OEM代码页解码失败时Ö变成替换字符
+        let mangled =
+            parse_line("  TCP    0.0.0.0:445    0.0.0.0:0    ABH\u{FFFD}REN    4").unwrap();
+        assert_eq!(mangled.state, "LISTENING");
+
+        let established =
+            parse_line("  TCP    10.0.0.2:50000    1.2.3.4:443    HERGESTELLT    7788").unwrap();
+        assert_eq!(established.state, "ESTABLISHED");
+
+        let closing =
+            parse_line("  TCP    10.0.0.2:50001    1.2.3.4:443    SCHLIESSEN_WARTEN    7788")
+                .unwrap();
+        assert_eq!(closing.state, "CLOSE_WAIT");
+    }
+
+    #[test]
+    fn parse_line_reads_linux_output() {
+        let conn = parse_line(
+            "tcp        0      0 0.0.0.0:22              0.0.0.0:*               LISTEN      812/sshd",
+        )
+        .unwrap();
+        assert_eq!(conn.local_address, "0.0.0.0:22");
+        assert_eq!(conn.state, "LISTENING");
+
+        let unknown = parse_line(
+            "tcp        0      0 127.0.0.1:631           0.0.0.0:*               LISTEN      -",
+        )
+        .unwrap();
+        assert_eq!(unknown.pid, "-");
+    }
+
+    #[test]
+    fn parse_line_skips_headers_and_short_lines() {
+        assert_eq!(parse_line("Active Connections"), None);
+        assert_eq!(
+            parse_line(
+                "  Proto  Local Address          Foreign Address        State           PID"
+            ),
+            None
+        );
+        assert_eq!(parse_line("TCP 0.0.0.0:80"), None);
+        assert_eq!(parse_line(""), None);
+    }
+
+    #[test]
+    fn parse_line_reads_ipv6_addresses() {
+        let windows = parse_line("  TCP    [::]:445    [::]:0    LISTENING    4").unwrap();
+        assert_eq!(windows.local_address, "[::]:445");
+        assert_eq!(address_family(&windows.local_address), AddressFamily::Ipv6);
+
+        let scoped =
+            parse_line("  TCP    [fe80::1%12]:5357    [fe80::2%12]:49700    ESTABLISHED    4")
+                .unwrap();
+        assert_eq!(scoped.foreign_address, "[fe80::2%12]:49700");
+
+        let linux =
+            parse_line("tcp6       0      0 :::22                   :::*                    LISTEN      812/sshd")
+                .unwrap();
+        assert_eq!(linux.local_address, ":::22");
+        assert_eq!(linux.state, "LISTENING");
+        assert_eq!(linux.pid, "812");
+        assert_eq!(address_family(&linux.local_address), AddressFamily::Ipv6);
+    }
+
+    #[test]
+    fn address_family_detects_ip_versions() {
+        assert_eq!(address_family("127.0.0.1:80"), AddressFamily::Ipv4);
+        assert_eq!(address_family("[::1]:80"), AddressFamily::Ipv6);
+        assert_eq!(address_family("::1:80"), AddressFamily::Ipv6);
+        assert_eq!(address_family("*:*"), AddressFamily::Unknown);
+    }
+
+    #[test]
+    fn is_loopback_checks_local_and_foreign_addresses() {
+        let local = |local: &str, foreign: &str| {
+            is_loopback(&conn(
+                &format!("TCP {} {} ESTABLISHED 1", local, foreign),
+                "x",
+            ))
+        };
+        assert!(local("127.0.0.1:8080", "0.0.0.0:0"));
+        assert!(local("127.0.0.53:53", "0.0.0.0:0"));
+        assert!(local("[::1]:631", "[::]:0"));
+        assert!(local("0.0.0.0:5000", "127.0.0.1:40000"));
+        assert!(!local("0.0.0.0:80", "0.0.0.0:0"));
+        assert!(!local("[::]:80", "[::]:0"));
+        assert!(!local("10.0.0.2:50000", "127.0.0.1:443"));
+    }
+
+    /// 记录写入内容的剪贴板，`fail`时模拟不可用的剪贴板
+    #[derive(Default)]
+    struct FakeClipboard {
+        text: Option<String>,
+        fail: bool,
+    }
+
+    impl Clipboard for FakeClipboard {
+        fn set_text(&mut self, text: &str) -> crate::error::Result<()> {
+            if self.fail {
+                return Err(Error::Other("no clipboard".to_string()));
+            }
+            self.text = Some(text.to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn copy_rendered_puts_the_plain_table_on_the_clipboard() {
+        let connections = vec![conn("TCP 0.0.0.0:80 0.0.0.0:0 LISTENING 10", "nginx")];
+        let args = args(&["--copy", "--width", "100"]);
+        let mut clipboard = FakeClipboard::default();
+        copy_rendered(&connections, &args, &Theme::default(), &mut clipboard).unwrap();
+
+        let mut expected = NoColor::new(Vec::new());
+        render(
+            &connections,
+            &args,
+            &Theme::default(),
+            Some(100),
+            &mut expected,
+        )
+        .unwrap();
+        let text = clipboard.text.unwrap();
+        assert_eq!(text, String::from_utf8(expected.into_inner()).unwrap());
+        assert!(text.contains("nginx") && !text.contains('\x1b'));
+
+        // 剪贴板不可用时只输出警告
+        let mut unavailable = FakeClipboard {
+            fail: true,
+            ..FakeClipboard::default()
+        };
+        assert!(copy_rendered(&connections, &args, &Theme::default(), &mut unavailable).is_ok());
+    }
+
+    #[test]
+    fn uptime_formats_the_time_since_the_resolved_start() {
+        let source = FakeSource(
+            "TCP 0.0.0.0:80 0.0.0.0:0 LISTENING 10\n\
+             TCP 0.0.0.0:9000 0.0.0.0:0 LISTENING 99\n",
+        );
+        let mut resolver = FakeResolver {
+            start_time: Some(fake_now() - Duration::from_secs(2 * 86_400 + 3 * 3_600 + 59)),
+            ..FakeResolver::default()
+        };
+        let connections =
+            resolve_connections(&source, &mut resolver, &args(&["--uptime"])).unwrap();
+        assert_eq!(connections[0].uptime.as_deref(), Some("2d 3h"));
+
+        // 启动时间无法读取时显示"?"
+        let mut resolver = FakeResolver::default();
+        let connections =
+            resolve_connections(&source, &mut resolver, &args(&["--uptime"])).unwrap();
+        assert_eq!(connections[1].uptime.as_deref(), Some("?"));
+    }
+
+    #[test]
+    fn stats_format_canned_memory_and_cpu_time() {
+        let source = FakeSource("TCP 0.0.0.0:80 0.0.0.0:0 LISTENING 10\n");
+        let stats = ProcessStats {
+            resident_bytes: 3 * 1024 * 1024,
+            cpu_time: Duration::from_secs(125),
+        };
+        let resolve = |flags: &[&str]| {
+            let mut resolver = FakeResolver {
+                stats: Some(stats),
+                ..FakeResolver::default()
+            };
+            resolve_connections(&source, &mut resolver, &args(flags))
+                .unwrap()
+                .remove(0)
+        };
+
+        let conn = resolve(&["--stats"]);
+        assert_eq!(conn.memory.as_deref(), Some("3 MiB"));
+        assert_eq!(conn.cpu_time.as_deref(), Some("2m 5s"));
+        assert_eq!(
+            resolve(&["--stats", "--si"]).memory.as_deref(),
+            Some("3.15 MB")
+        );
+        assert_eq!(resolve(&[]).memory, None);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn export_sqlite_writes_rows_with_a_shared_capture_time() {
+        let mut nginx = conn("TCP 0.0.0.0:80 0.0.0.0:0 LISTENING 10", "nginx");
+        nginx.user = Some("www-data".to_string());
+        let connections = vec![nginx, conn("UDP 0.0.0.0:53 *:* 13", "dnsmasq")];
+
+        let db = rusqlite::Connection::open_in_memory().unwrap();
+        let before = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        export_sqlite(&db, &connections).unwrap();
+        // 再次导出时追加行，不重新建表
+        export_sqlite(&db, &connections[..1]).unwrap();
+
+        let mut query = db
+            .prepare(
+                "SELECT captured_at, protocol || ' ' || local_address || ' ' || state || ' ' ||
+                    pid || ' ' || process_name || ' ' || IFNULL(user, 'NULL')
+                 FROM connections ORDER BY id",
+            )
+            .unwrap();
+        let rows: Vec<(i64, String)> = query
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert!(rows[0].0 >= before && rows.iter().all(|(at, _)| *at >= rows[0].0));
+        assert_eq!(rows[0].0, rows[1].0);
+        let fields: Vec<&str> = rows.iter().map(|(_, fields)| fields.as_str()).collect();
+        assert_eq!(
+            fields,
+            [
+                "TCP 0.0.0.0:80 LISTENING 10 nginx www-data",
+                "UDP 0.0.0.0:53 - 13 dnsmasq NULL",
+                "TCP 0.0.0.0:80 LISTENING 10 nginx www-data",
+            ]
+        );
+    }
+
+    #[test]
+    fn long_paths_are_ellipsized_to_the_output_width() {
+        let mut long = conn("TCP 0.0.0.0:80 0.0.0.0:0 LISTENING 10", "nginx");
+        long.process_path = format!("/opt/{}/bin/nginx", "very-long-directory".repeat(8));
+        let connections = vec![long];
+        for width in [90, 120] {
+            let mut out = NoColor::new(Vec::new());
+            render(
+                &connections,
+                &args(&[]),
+                &Theme::default(),
+                Some(width),
+                &mut out,
+            )
+            .unwrap();
+            let text = String::from_utf8(out.into_inner()).unwrap();
+            let row = text.lines().find(|line| line.contains("nginx")).unwrap();
+            assert_eq!(row.chars().count(), width, "{}", row);
+            assert!(row.ends_with('…') && row.contains(" /opt/very-long"));
+        }
+
+        // 未指定宽度时保留完整路径
+        let mut out = NoColor::new(Vec::new());
+        render(&connections, &args(&[]), &Theme::default(), None, &mut out).unwrap();
+        let text = String::from_utf8(out.into_inner()).unwrap();
+        assert!(text.contains(&connections[0].process_path));
+    }
+
+    #[test]
+    fn count_connections_tallies_protocols_and_states() {
+        let connections = vec![
+            conn("TCP 0.0.0.0:80 0.0.0.0:0 LISTENING 10", "nginx"),
+            conn("TCP [::]:80 [::]:0 LISTENING 11", "apache"),
+            conn("TCP 10.0.0.2:5000 1.2.3.4:443 ESTABLISHED 12", "curl"),
+            conn("UDP 0.0.0.0:53 *:* 13", "dnsmasq"),
+        ];
+        assert_eq!(
+            count_connections(&connections),
+            ConnectionCounts {
+                total: 4,
+                tcp: 3,
+                udp: 1,
+                listening: 2,
+                established: 1,
+            }
+        );
+
+        let mut out = NoColor::new(Vec::new());
+        render(
+            &connections,
+            &args(&["--format", "count"]),
+            &Theme::default(),
+            None,
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out.into_inner()).unwrap(),
+            "total=4\ntcp=3\nudp=1\nlistening=2\nestablished=1\n"
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn system_pids_resolve_without_a_handle() {
+        assert_eq!(system_process_name("4"), Some("System"));
+        assert_eq!(system_process_name("0"), Some("System Idle Process"));
+        assert_eq!(system_process_name("1234"), None);
+        assert_eq!(
+            get_process_info("4").unwrap(),
+            ("System".to_string(), "-".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_connections_filters_by_protocol_and_foreign_port() {
+        let netstat = "\
+  TCP    10.0.0.2:5000     1.2.3.4:443      ESTABLISHED    13
+  TCP    10.0.0.2:5001     1.2.3.4:80       ESTABLISHED    13
+  UDP    0.0.0.0:5353      *:*                             14
+";
+        let https = parse_connections(netstat, &args(&["--foreign-port", "443"]));
+        assert_eq!(https.len(), 1);
+        assert_eq!(https[0].local_address, "10.0.0.2:5000");
+
+        let udp = parse_connections(netstat, &args(&["--udp"]));
+        assert_eq!(udp.len(), 1);
+        assert_eq!(udp[0].protocol, "UDP");
+    }
+
+    #[test]
+    fn address_columns_align_across_short_and_long_addresses() {
+        let connections = vec![
+            conn("TCP 1.2.3.4:80 5.6.7.8:1 ESTABLISHED 10", "nginx"),
+            conn(
+                "TCP [2001:db8::1234:5678]:443 [2001:db8::abcd:ef01]:51000 ESTABLISHED 11",
+                "sshd",
+            ),
+            conn("UDP 0.0.0.0:53 *:* 13", "dnsmasq"),
+        ];
+        let mut out = NoColor::new(Vec::new());
+        render(&connections, &args(&[]), &Theme::default(), None, &mut out).unwrap();
+        let text = String::from_utf8(out.into_inner()).unwrap();
+
+        let header = text
+            .lines()
+            .find(|line| line.starts_with("PROTOCOL"))
+            .unwrap();
+        for (column, values) in [
+            (
+                "FOREIGN ADDRESS",
+                ["5.6.7.8:1", "[2001:db8::abcd:ef01]:51000", "*:*"],
+            ),
+            ("STATE", ["ESTABLISHED", "ESTABLISHED", "-"]),
+        ] {
+            let start = header.find(column).unwrap();
+            for (conn, value) in connections.iter().zip(values) {
+                let row = text
+                    .lines()
+                    .find(|line| line.contains(&conn.process_name))
+                    .unwrap();
+                assert_eq!(row[start..].split(' ').next(), Some(value), "{}", row);
+            }
+        }
+    }
+
+    #[test]
+    fn total_only_prints_just_the_count() {
+        let connections = vec![
+            conn("TCP 0.0.0.0:80 0.0.0.0:0 LISTENING 10", "nginx"),
+            conn("UDP 0.0.0.0:53 *:* 13", "dnsmasq"),
+        ];
+        let mut out = NoColor::new(Vec::new());
+        render(
+            &connections,
+            &args(&["--total-only"]),
+            &Theme::default(),
+            None,
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(String::from_utf8(out.into_inner()).unwrap(), "2\n");
+    }
+
+    #[test]
+    fn input_file_is_parsed_and_rendered() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("netstat.txt");
+        std::fs::write(
+            &path,
+            "Active Connections\n\n  Proto  Local Address          Foreign Address        State           PID\n  \
+             TCP    0.0.0.0:80             0.0.0.0:0              LISTENING       10\n  \
+             UDP    0.0.0.0:53             *:*                                    11\n",
+        )
+        .unwrap();
+        let args = args(&["--input", path.to_str().unwrap()]);
+        let connections = resolve_connections(
+            connection_source(&args).as_ref(),
+            &mut FakeResolver::default(),
+            &args,
+        )
+        .unwrap();
+
+        let mut out = NoColor::new(Vec::new());
+        render(&connections, &args, &Theme::default(), None, &mut out).unwrap();
+        let rows = normalized(&String::from_utf8(out.into_inner()).unwrap());
+        assert_eq!(
+            rows[2..],
+            [
+                "TCP 0.0.0.0:80 0.0.0.0:0 LISTENING 10 nginx /usr/bin/nginx",
+                "UDP 0.0.0.0:53 *:* - 11 sshd /usr/bin/sshd",
+            ]
+        );
+
+        let missing = dir.path().join("missing.txt");
+        let err = NetstatFile(missing.clone()).netstat_output().unwrap_err();
+        assert!(matches!(err, Error::PathAccess(path) if path == missing));
+    }
+
+    #[test]
+    fn group_by_state_orders_listening_then_established_then_others() {
+        let connections = vec![
+            conn("UDP 0.0.0.0:53 *:* 13", "dnsmasq"),
+            conn("TCP 10.0.0.2:5000 1.2.3.4:443 ESTABLISHED 12", "curl"),
+            conn("TCP 10.0.0.2:5001 1.2.3.4:443 TIME_WAIT 12", "curl"),
+            conn("TCP 0.0.0.0:80 0.0.0.0:0 LISTENING 10", "nginx"),
+            conn("TCP 10.0.0.2:5002 1.2.3.4:80 ESTABLISHED 14", "wget"),
+        ];
+        let mut out = NoColor::new(Vec::new());
+        render(
+            &connections,
+            &args(&["--group-by-state"]),
+            &Theme::default(),
+            None,
+            &mut out,
+        )
+        .unwrap();
+        let output = String::from_utf8(out.into_inner()).unwrap();
+        let lines: Vec<String> = output
+            .lines()
+            .skip(3)
+            .map(|line| match line.starts_with('─') {
+                true => line.to_string(),
+                false => line
+                    .split_whitespace()
+                    .take(2)
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            })
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "── LISTENING ──",
+                "TCP 0.0.0.0:80",
+                "── ESTABLISHED ──",
+                "TCP 10.0.0.2:5000",
+                "TCP 10.0.0.2:5002",
+                "── OTHER ──",
+                "UDP 0.0.0.0:53",
+                "TCP 10.0.0.2:5001",
+            ]
+        );
+    }
+
+    #[test]
+    fn dedup_by_process_prefers_listening_rows() {
+        let client = conn("TCP 10.0.0.2:5000 1.2.3.4:443 ESTABLISHED 10", "nginx");
+        let listener = conn("TCP 0.0.0.0:80 0.0.0.0:0 LISTENING 10", "nginx");
+        let mut unknown = conn("TCP 0.0.0.0:81 0.0.0.0:0 LISTENING -", "x");
+        unknown.process_name = "Unknown".to_string();
+
+        let rows = dedup_by_process(&[&client, &listener, &unknown]);
+        assert_eq!(rows, vec![(&listener, 1), (&unknown, 0)]);
+    }
+
+    #[test]
+    fn diff_connections_marks_added_unchanged_and_closed() {
+        let kept = conn("TCP 0.0.0.0:80 0.0.0.0:0 LISTENING 10", "nginx");
+        let closed = conn("TCP 10.0.0.2:5000 1.2.3.4:443 ESTABLISHED 20", "curl");
+        let added = conn("TCP 10.0.0.2:5001 1.2.3.4:443 ESTABLISHED 20", "curl");
+        let previous = vec![kept.clone(), closed.clone()];
+        let current = vec![kept.clone(), added.clone()];
+
+        let diff = diff_connections(&previous, &current);
+        assert_eq!(
+            diff,
+            vec![
+                (&current[0], ConnectionChange::Unchanged),
+                (&current[1], ConnectionChange::Added),
+                (&previous[1], ConnectionChange::Closed),
+            ]
+        );
+    }
+
+    #[test]
+    fn within_ancestry_respects_depth() {
+        let parents: HashMap<String, String> = [("3", "2"), ("2", "1"), ("1", "1")]
+            .into_iter()
+            .map(|(pid, parent)| (pid.to_string(), parent.to_string()))
+            .collect();
+        assert!(within_ancestry("1", "1", 0, &parents));
+        assert!(within_ancestry("3", "1", usize::MAX, &parents));
+        assert!(within_ancestry("3", "2", 1, &parents));
+        assert!(!within_ancestry("3", "1", 1, &parents));
+        assert!(!within_ancestry("1", "3", usize::MAX, &parents));
+        assert!(!within_ancestry("99", "1", usize::MAX, &parents));
+    }
+
+    #[test]
+    fn no_stripes_drops_row_backgrounds() {
+        let connections = vec![
+            conn("TCP 0.0.0.0:80 0.0.0.0:0 LISTENING 10", "nginx"),
+            conn("TCP 0.0.0.0:22 0.0.0.0:0 LISTENING 11", "sshd"),
+            conn("UDP 0.0.0.0:53 *:* 13", "dnsmasq"),
+        ];
+        let render_ansi = |flags: &[&str]| {
+            let mut out = termcolor::Buffer::ansi();
+            render(
+                &connections,
+                &args(flags),
+                &Theme::default(),
+                None,
+                &mut out,
+            )
+            .unwrap();
+            String::from_utf8(out.into_inner()).unwrap()
+        };
+        assert!(render_ansi(&[]).contains("\x1b[48;"));
+        assert!(!render_ansi(&["--no-stripes"]).contains("\x1b[48;"));
+    }
+
+    #[test]
+    fn render_writes_the_table_into_a_buffer() {
+        let connections = vec![
+            conn("TCP 0.0.0.0:80 0.0.0.0:0 LISTENING 10", "nginx"),
+            conn("UDP 0.0.0.0:53 *:* 13", "dnsmasq"),
+        ];
+        let mut out = termcolor::Buffer::no_color();
+        render(&connections, &args(&[]), &Theme::default(), None, &mut out).unwrap();
+        let rows = normalized(&String::from_utf8(out.into_inner()).unwrap());
+        assert_eq!(
+            rows,
+            [
+                "",
+                "PROTOCOL LOCAL ADDRESS FOREIGN ADDRESS STATE PID PROCESS PATH",
+                "TCP 0.0.0.0:80 0.0.0.0:0 LISTENING 10 nginx /usr/bin/nginx",
+                "UDP 0.0.0.0:53 *:* - 13 dnsmasq /usr/bin/dnsmasq",
+            ]
+        );
+    }
+
+    #[test]
+    fn render_writes_json_rows() {
+        let connections = vec![
+            conn("TCP 0.0.0.0:80 0.0.0.0:0 LISTENING 10", "nginx"),
+            conn("UDP 0.0.0.0:53 *:* 13", "dnsmasq"),
+        ];
+        let render_json = |flags: &[&str]| {
+            let mut out = NoColor::new(Vec::new());
+            render(
+                &connections,
+                &args(flags),
+                &Theme::default(),
+                None,
+                &mut out,
+            )
+            .unwrap();
+            String::from_utf8(out.into_inner()).unwrap()
+        };
+
+        let compact = render_json(&["--format", "json", "--limit", "1"]);
+        let json: serde_json::Value = serde_json::from_str(&compact).unwrap();
+        assert_eq!(json.as_array().unwrap().len(), 1);
+        assert_eq!(json[0]["process_name"], "nginx");
+        assert!(json[0].get("user").is_none());
+
+        let pretty = render_json(&["--format", "json", "--limit", "1", "--pretty"]);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&pretty).unwrap(),
+            json
+        );
+    }
+
+    #[test]
+    fn parse_connections_exposed_hides_loopback_listeners() {
+        let netstat = "\
+  TCP    0.0.0.0:80        0.0.0.0:0        LISTENING      10
+  TCP    127.0.0.1:8080    0.0.0.0:0        LISTENING      11
+  TCP    [::1]:9000        [::]:0           LISTENING      12
+  TCP    10.0.0.2:5000     1.2.3.4:443      ESTABLISHED    13
+";
+        let shown: Vec<String> = parse_connections(netstat, &args(&["--exposed"]))
+            .into_iter()
+            .map(|conn| conn.local_address)
+            .collect();
+        assert_eq!(shown, vec!["0.0.0.0:80"]);
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn get_process_info_warns_when_a_process_cannot_be_opened() {
+        use std::sync::Mutex;
+
+        /// 记录所有日志的测试logger
+        struct Capture(Mutex<Vec<(log::Level, String)>>);
+
+        impl log::Log for Capture {
+            fn enabled(&self, _: &log::Metadata) -> bool {
+                true
+            }
+
+            fn log(&self, record: &log::Record) {
+                let entry = (record.level(), record.args().to_string());
+                self.0.lock().unwrap().push(entry);
+            }
+
+            fn flush(&self) {}
+        }
+
+        static LOGGER: Capture = Capture(Mutex::new(Vec::new()));
+        log::set_logger(&LOGGER).expect("no other test installs a logger");
+        log::set_max_level(log::LevelFilter::Debug);
+
+        // 超出任何系统pid_max的PID，对应的进程不可能存在
+        let info = get_process_info("999999999").unwrap();
+        assert_eq!(info, ("Unknown".to_string(), "Unknown".to_string()));
+        assert!(LOGGER
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|(level, message)| *level == log::Level::Warn && message.contains("999999999")));
+    }
+}
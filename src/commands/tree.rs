@@ -2,109 +2,1672 @@
 //!
 //! 本模块提供以树状格式显示目录结构的功能，
 //! 支持多种显示选项和排序方式。
+//!
+//! 输出是流式的：每个条目在遍历到时立即打印，不会先收集整棵树。
+//! 排序只在单个目录的直接子条目内进行，内存占用与最大目录的条目数成正比；
+//! `--no-sort`时按文件系统返回的顺序逐个读取子条目，不做任何缓冲。
 
 use crate::cli::TreeArgs;
-use crate::config::{Config, SortBy};
+use crate::config::{Config, OutputFormat, PathDisplay, SortBy};
 use crate::error::{Error, Result};
+use crate::logging::{self, Level};
 use crate::utils;
-use colored::*;
-use humansize::{format_size, BINARY};
+use colored::Color;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
-use std::path::Path;
-use std::time::SystemTime;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant, SystemTime};
 use walkdir::{DirEntry, WalkDir};
 
-/// 实现从walkdir::Error到自定义错误类型的转换
+/// 实现从walkdir::Error到自定义错误类型的转换，保留出错条目的路径和深度
 impl From<walkdir::Error> for Error {
     fn from(err: walkdir::Error) -> Self {
-        Error::Io(err.into())
+        // 优先使用底层I/O错误信息，避免与路径重复
+        let message = err
+            .io_error()
+            .map(|io| io.to_string())
+            .unwrap_or_else(|| err.to_string());
+        Error::Traversal {
+            path: err.path().map(Path::to_path_buf),
+            depth: err.depth(),
+            message,
+        }
+    }
+}
+
+/// Helper extension trait for DirEntry to create entries from paths
+trait DirEntryExt {
+    /// 创建树根条目
+    ///
+    /// 根路径是用户显式指定的，为指向目录的符号链接时默认跟随，
+    /// 条目类型为链接目标（路径仍保留用户给出的形式）；
+    /// 子条目中的符号链接不受影响。`--no-deref-args`时不跟随。
+    fn root(path: &Path, config: &Config) -> Result<DirEntry>;
+}
+
+/// Implementation of the DirEntryExt trait for DirEntry
+impl DirEntryExt for DirEntry {
+    fn root(path: &Path, config: &Config) -> Result<DirEntry> {
+        let walker = WalkDir::new(path).follow_links(!config.no_deref_args);
+        Ok(walker.into_iter().next().unwrap()?)
+    }
+}
+
+/// 使用给定的参数和配置执行tree命令
+///
+/// 指定`--stdin`或路径为`-`时，从标准输入读取根目录列表，
+/// 否则显示`args.root()`对应的目录树。根路径为文件时只输出该文件一行
+/// （按参数附带大小、日期等信息）；不存在或无法读取时返回`Error::PathAccess`。
+///
+/// # 参数
+/// * `args` - 包含路径、最大深度、过滤模式等参数的TreeArgs结构体
+/// * `config` - 包含显示选项、排序方式、过滤条件等运行时配置
+///
+/// # 返回
+/// * `Ok(())` 命令执行成功时返回
+/// * `Err(Error)` 执行过程中发生错误时返回
+pub fn execute(args: &TreeArgs, config: &Config) -> Result<()> {
+    let stdout = io::stdout();
+    execute_with(args, config, stdout.is_terminal(), &mut stdout.lock())
+}
+
+/// `execute`的实现，`is_terminal`为标准输出是否为终端
+///
+/// 不分页时输出写入`out`。
+fn execute_with(
+    args: &TreeArgs,
+    config: &Config,
+    is_terminal: bool,
+    out: &mut dyn Write,
+) -> Result<()> {
+    // `--format auto`按标准输出是否为终端选择树形或路径列表；分页时输出到终端
+    let config = &config.clone().with_resolved_format(is_terminal);
+
+    // 仅当标准输出为终端时分页，重定向时直接输出
+    if args.pager && is_terminal {
+        if let Some(mut pager) = utils::spawn_pager() {
+            let result = match pager.stdin.take() {
+                Some(mut input) => execute_to(args, config, &mut input),
+                None => Ok(()),
+            };
+            pager.wait()?;
+            // 用户提前退出分页器时管道关闭，不视为错误
+            return match result {
+                Err(Error::Io(err)) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+                other => other,
+            };
+        }
+    }
+
+    execute_to(args, config, out)
+}
+
+/// 将tree命令的输出写入指定的输出流
+fn execute_to(args: &TreeArgs, config: &Config, out: &mut dyn Write) -> Result<()> {
+    let root = args.root();
+    if args.stdin || root == Path::new("-") {
+        let stdin = io::stdin();
+        return execute_roots(stdin.lock(), config, out);
+    }
+
+    if let Some(other) = &args.diff {
+        check_dir(root)?;
+        check_dir(other)?;
+        return execute_diff(root, other, config, out);
+    }
+
+    // 根路径为文件时只输出该文件本身（包括请求的大小、日期等元数据）
+    if !root.is_file() {
+        check_dir(root)?;
+    }
+    render_root(root, config, out)
+}
+
+/// 检查路径是否为可读取的目录
+///
+/// # 返回
+/// * `Err(Error::PathAccess)` 路径不存在、不是目录或无法读取时返回
+fn check_dir(root: &Path) -> Result<()> {
+    if !root.is_dir() || fs::read_dir(root).is_err() {
+        return Err(Error::PathAccess(root.to_path_buf()));
+    }
+    Ok(())
+}
+
+/// diff模式下单个条目的比较结果
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiffStatus {
+    /// 仅存在于当前目录
+    Added,
+    /// 仅存在于对比目录
+    Removed,
+    /// 两边都存在但文件大小不同
+    Changed,
+    /// 两边都存在且无差异
+    Unchanged,
+}
+
+/// 比较两个目录树，按相对路径返回每个条目的差异状态
+///
+/// 两侧条目都会先经过`filter_entry`过滤，结果按相对路径排序。
+///
+/// # 参数
+/// * `root` - 当前目录
+/// * `other` - 作为基准的对比目录
+/// * `config` - 过滤配置
+pub fn diff_entries(root: &Path, other: &Path, config: &Config) -> Vec<(PathBuf, DiffStatus)> {
+    compare_trees(root, other, config, &mut Progress::disabled())
+}
+
+/// `diff_entries`的实现，遍历过程中更新进度显示
+fn compare_trees(
+    root: &Path,
+    other: &Path,
+    config: &Config,
+    progress: &mut Progress,
+) -> Vec<(PathBuf, DiffStatus)> {
+    let current = collect_relative(root, config, progress);
+    let mut baseline = collect_relative(other, config, progress);
+    progress.clear();
+    let mut result = Vec::with_capacity(current.len());
+
+    for (path, (is_dir, size)) in current {
+        let status = match baseline.remove(&path) {
+            None => DiffStatus::Added,
+            Some((other_is_dir, other_size)) => {
+                if !is_dir && !other_is_dir && size != other_size {
+                    DiffStatus::Changed
+                } else {
+                    DiffStatus::Unchanged
+                }
+            }
+        };
+        result.push((path, status));
+    }
+
+    result.extend(baseline.into_keys().map(|path| (path, DiffStatus::Removed)));
+    result.sort_by(|a, b| a.0.cmp(&b.0));
+    result
+}
+
+/// 收集目录下所有通过过滤的条目，键为相对路径，值为(是否目录, 文件大小)
+fn collect_relative(
+    root: &Path,
+    config: &Config,
+    progress: &mut Progress,
+) -> BTreeMap<PathBuf, (bool, u64)> {
+    WalkDir::new(root)
+        .min_depth(1)
+        .max_depth(config.max_depth.unwrap_or(usize::MAX))
+        .into_iter()
+        .filter_entry(|e| filter_entry(e, &NodeMetadata::from_entry(e), config))
+        .filter_map(|e| e.ok())
+        .inspect(|_| progress.tick())
+        .filter_map(|e| {
+            let relative = e.path().strip_prefix(root).ok()?.to_path_buf();
+            let is_dir = e.file_type().is_dir();
+            let size = e.metadata().map(|m| m.len()).unwrap_or(0);
+            Some((relative, (is_dir, size)))
+        })
+        .collect()
+}
+
+/// 以差异标注的形式输出两个目录的比较结果
+fn execute_diff(root: &Path, other: &Path, config: &Config, out: &mut dyn Write) -> Result<()> {
+    let entries = compare_trees(root, other, config, &mut Progress::for_terminal());
+    for (path, status) in entries {
+        let line = format!("{}", path.display());
+        match status {
+            DiffStatus::Added => writeln!(out, "{}", diff_line('+', &line, Color::Green, config))?,
+            DiffStatus::Removed => writeln!(out, "{}", diff_line('-', &line, Color::Red, config))?,
+            DiffStatus::Changed => {
+                writeln!(out, "{}", diff_line('~', &line, Color::Yellow, config))?
+            }
+            DiffStatus::Unchanged => writeln!(out, "  {}", line)?,
+        }
+    }
+
+    Ok(())
+}
+
+/// 以给定颜色输出带差异前缀的一行
+fn diff_line(mark: char, line: &str, color: Color, config: &Config) -> String {
+    utils::styled(
+        &format!("{} {}", mark, line),
+        Some(color),
+        false,
+        config.color,
+    )
+}
+
+/// 从输入流读取换行分隔的路径，并逐个作为根目录显示
+///
+/// 空行会被跳过，不存在的路径输出到标准错误后继续处理下一个。
+///
+/// # 参数
+/// * `reader` - 路径列表的输入源（通常为标准输入）
+/// * `config` - 显示配置
+pub fn execute_roots<R: BufRead>(reader: R, config: &Config, out: &mut dyn Write) -> Result<()> {
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let root = PathBuf::from(line);
+        if !root.exists() {
+            logging::warn(format!("{}: No such file or directory", root.display()));
+            continue;
+        }
+
+        render_root(&root, config, out)?;
+    }
+
+    Ok(())
+}
+
+/// git状态映射，键为相对于树根目录的路径，值为porcelain状态码
+pub type GitStatusMap = HashMap<PathBuf, String>;
+
+/// 读取根目录所在仓库的git状态
+///
+/// 只调用一次`git status --porcelain`，根目录不在git仓库中时返回`None`
+fn load_git_status(root: &Path) -> Option<GitStatusMap> {
+    let run_git = |args: &[&str]| {
+        Command::new("git")
+            .arg("-C")
+            .arg(root)
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+    };
+
+    let toplevel = run_git(&["rev-parse", "--show-toplevel"])?;
+    let toplevel = PathBuf::from(String::from_utf8_lossy(&toplevel.stdout).trim());
+    let status = run_git(&["status", "--porcelain", "--ignored"])?;
+
+    let toplevel = fs::canonicalize(toplevel).ok()?;
+    let root = fs::canonicalize(root).ok()?;
+    let base = root.strip_prefix(&toplevel).ok()?;
+
+    Some(parse_git_status(
+        &String::from_utf8_lossy(&status.stdout),
+        base,
+    ))
+}
+
+/// 解析`git status --porcelain`输出
+///
+/// porcelain输出的路径相对于仓库根目录，`base`为树根相对于仓库根的路径，
+/// 不在树根之下的条目会被忽略。重命名条目取新路径。
+///
+/// # 参数
+/// * `porcelain` - `git status --porcelain`的原始输出
+/// * `base` - 树根目录相对于仓库根目录的路径
+pub fn parse_git_status(porcelain: &str, base: &Path) -> GitStatusMap {
+    let mut map = GitStatusMap::new();
+
+    for line in porcelain.lines() {
+        if line.len() < 4 {
+            continue;
+        }
+
+        let code = line[..2].trim().to_string();
+        let path = &line[3..];
+        let path = path.rsplit(" -> ").next().unwrap_or(path);
+        let path = path.trim_matches('"').trim_end_matches('/');
+
+        if let Ok(relative) = Path::new(path).strip_prefix(base) {
+            map.insert(relative.to_path_buf(), code);
+        }
+    }
+
+    map
+}
+
+/// 目录树的条目统计（不含根目录本身；根路径为文件时只统计该文件）
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct TreeSummary {
+    /// 目录数
+    pub directories: usize,
+    /// 文件数（包括符号链接）
+    pub files: usize,
+    /// 文件总字节数
+    pub bytes: u64,
+}
+
+impl TreeSummary {
+    /// 累加单个条目，文件大小经`links`去重硬链接
+    fn add(&mut self, metadata: &NodeMetadata, links: &mut SeenLinks) {
+        if metadata.is_dir {
+            self.directories += 1;
+        } else {
+            self.files += 1;
+            self.bytes += links.size(metadata);
+        }
+    }
+
+    /// 累加另一份统计（如子目录的统计）
+    fn merge(&mut self, other: &TreeSummary) {
+        self.directories += other.directories;
+        self.files += other.files;
+        self.bytes += other.bytes;
+    }
+}
+
+/// 统计大小时已经计入的硬链接
+///
+/// 启用`--count-links-once`时，指向同一文件（设备号和inode号相同）的多个硬链接
+/// 只计入一次大小，与`du`相同；未启用或在非Unix系统上每个条目都计入。
+/// 指定`--skip-larger-than`时超过阈值的文件不计入大小。
+struct SeenLinks {
+    /// 是否启用硬链接去重
+    enabled: bool,
+    /// 已计入的（设备号, inode号）
+    seen: HashSet<(u64, u64)>,
+    /// 计入大小的文件上限（字节）
+    skip_larger_than: Option<u64>,
+}
+
+impl SeenLinks {
+    fn new(config: &Config) -> Self {
+        Self {
+            enabled: config.count_links_once,
+            seen: HashSet::new(),
+            skip_larger_than: config.skip_larger_than,
+        }
+    }
+
+    /// 返回条目应计入的字节数，已计入过的硬链接和超过阈值的文件返回0
+    fn size(&mut self, metadata: &NodeMetadata) -> u64 {
+        if too_large(metadata, self.skip_larger_than) {
+            return 0;
+        }
+        if let Some(id) = metadata.link_id.filter(|_| self.enabled) {
+            if !self.seen.insert(id) {
+                return 0;
+            }
+        }
+        metadata.size.unwrap_or(0)
+    }
+}
+
+/// 判断文件是否超过`--skip-larger-than`阈值，超过的文件不计算摘要和大小
+fn too_large(metadata: &NodeMetadata, limit: Option<u64>) -> bool {
+    !metadata.is_dir && limit.is_some_and(|limit| metadata.size.is_some_and(|size| size > limit))
+}
+
+/// 单个扩展名的文件统计（`--stats-by-ext`）
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtensionStats {
+    /// 小写扩展名（不含点），无扩展名时为`(none)`
+    pub extension: String,
+    /// 文件数
+    pub files: usize,
+    /// 文件总字节数
+    pub bytes: u64,
+}
+
+/// 按配置遍历目录，按扩展名统计文件数和总字节数
+///
+/// 与树形输出使用相同的过滤条件和深度限制，目录本身不计入。
+/// 结果按文件数降序排列，文件数相同时按扩展名排列。
+///
+/// # 参数
+/// * `root` - 根目录路径
+/// * `config` - 过滤和深度配置
+pub fn stats_by_extension(root: &Path, config: &Config) -> Result<Vec<ExtensionStats>> {
+    stats_by_extension_with_progress(root, config, &mut Progress::disabled())
+}
+
+/// `stats_by_extension`的实现，遍历过程中更新进度显示
+fn stats_by_extension_with_progress(
+    root: &Path,
+    config: &Config,
+    progress: &mut Progress,
+) -> Result<Vec<ExtensionStats>> {
+    fn visit(
+        entry: &TreeEntry,
+        config: &Config,
+        tally: &mut HashMap<String, (usize, u64)>,
+        links: &mut SeenLinks,
+        progress: &mut Progress,
+    ) {
+        if !entry.is_dir() {
+            let extension = entry.entry.path().extension().map_or_else(
+                || "(none)".to_string(),
+                |ext| utils::escape_os_str(ext).to_lowercase(),
+            );
+            let stats = tally.entry(extension).or_default();
+            stats.0 += 1;
+            stats.1 += links.size(&entry.metadata);
+        } else if should_descend(entry, config) {
+            for child in read_children(entry, config) {
+                progress.tick();
+                visit(&child, config, tally, links, progress);
+            }
+        }
+    }
+
+    let mut tally = HashMap::new();
+    let root_entry = TreeEntry::new(DirEntry::root(root, config)?, 0);
+    visit(
+        &root_entry,
+        config,
+        &mut tally,
+        &mut SeenLinks::new(config),
+        progress,
+    );
+    progress.clear();
+
+    let mut stats: Vec<ExtensionStats> = tally
+        .into_iter()
+        .map(|(extension, (files, bytes))| ExtensionStats {
+            extension,
+            files,
+            bytes,
+        })
+        .collect();
+    stats.sort_by(|a, b| {
+        b.files
+            .cmp(&a.files)
+            .then_with(|| a.extension.cmp(&b.extension))
+    });
+    Ok(stats)
+}
+
+/// 按配置遍历目录并统计目录数、文件数和总字节数
+///
+/// 与树形输出使用相同的过滤条件和深度限制，但不打印任何条目。
+///
+/// # 参数
+/// * `root` - 根目录路径
+/// * `config` - 过滤和深度配置
+pub fn summarize(root: &Path, config: &Config) -> Result<TreeSummary> {
+    summarize_with_progress(root, config, &mut Progress::disabled())
+}
+
+/// `summarize`的实现，遍历过程中更新进度显示
+fn summarize_with_progress(
+    root: &Path,
+    config: &Config,
+    progress: &mut Progress,
+) -> Result<TreeSummary> {
+    fn visit(
+        entry: &TreeEntry,
+        config: &Config,
+        summary: &mut TreeSummary,
+        links: &mut SeenLinks,
+        progress: &mut Progress,
+    ) {
+        if !should_descend(entry, config) {
+            return;
+        }
+        for child in read_children(entry, config) {
+            progress.tick();
+            summary.add(&child.metadata, links);
+            visit(&child, config, summary, links, progress);
+        }
+    }
+
+    let mut summary = TreeSummary::default();
+    let mut links = SeenLinks::new(config);
+    let root_entry = TreeEntry::new(DirEntry::root(root, config)?, 0);
+    // 根路径为文件时统计该文件本身
+    if !root_entry.is_dir() {
+        summary.add(&root_entry.metadata, &mut links);
+    }
+    visit(&root_entry, config, &mut summary, &mut links, progress);
+    progress.clear();
+    Ok(summary)
+}
+
+/// 遍历耗时较长时在stderr显示已扫描的条目数
+///
+/// 用于输出前需要完整遍历的模式（计数、比较、JSON/XML、汇总树、扩展名统计和大小列对齐）。
+/// 仅在标准输出和标准错误都是终端、且遍历超过500毫秒后才显示，
+/// 进度行原地刷新，并在输出结果前清除，避免与结果混在一起。
+struct Progress {
+    /// 是否允许显示进度
+    enabled: bool,
+    /// 遍历开始时间
+    start: Instant,
+    /// 已扫描的条目数
+    scanned: usize,
+    /// 进度行当前是否显示在屏幕上
+    visible: bool,
+}
+
+impl Progress {
+    /// 显示进度之前的最短遍历时间
+    const DELAY: Duration = Duration::from_millis(500);
+
+    /// 创建进度显示，非终端或`--quiet`时自动禁用
+    fn for_terminal() -> Self {
+        let enabled = io::stdout().is_terminal()
+            && io::stderr().is_terminal()
+            && logging::enabled(Level::Normal);
+        Self {
+            enabled,
+            ..Self::disabled()
+        }
+    }
+
+    /// 创建不显示任何内容的进度（供库接口使用）
+    fn disabled() -> Self {
+        Self {
+            enabled: false,
+            start: Instant::now(),
+            scanned: 0,
+            visible: false,
+        }
+    }
+
+    /// 记录一个已扫描的条目，必要时刷新进度行
+    fn tick(&mut self) {
+        self.scanned += 1;
+        // 每64个条目检查一次计时器，减少系统调用
+        if !self.enabled || !self.scanned.is_multiple_of(64) || self.start.elapsed() < Self::DELAY {
+            return;
+        }
+        eprint!("\rscanning… {} entries", self.scanned);
+        self.visible = true;
+    }
+
+    /// 清除进度行
+    fn clear(&mut self) {
+        if self.visible {
+            eprint!("\r\x1b[2K");
+            self.visible = false;
+        }
+    }
+}
+
+/// 以单个路径为根目录显示目录树
+fn render_root(root: &Path, config: &Config, out: &mut dyn Write) -> Result<()> {
+    if config.count_only {
+        let summary = summarize_with_progress(root, config, &mut Progress::for_terminal())?;
+        writeln!(
+            out,
+            "directories={} files={} bytes={}",
+            summary.directories, summary.files, summary.bytes
+        )?;
+        return Ok(());
+    }
+
+    if let Some(depth) = config.only_depth {
+        let root_entry = TreeEntry::new(DirEntry::root(root, config)?, 0);
+        write_depth_slice(&root_entry, root, depth, config, out)?;
+        return Ok(());
+    }
+
+    if let Some(pattern) = &config.find_dirs {
+        let root_entry = TreeEntry::new(DirEntry::root(root, config)?, 0);
+        write_found_dirs(&root_entry, pattern, config, out)?;
+        return Ok(());
+    }
+
+    if config.stats_by_ext {
+        let stats = stats_by_extension_with_progress(root, config, &mut Progress::for_terminal())?;
+        let width = stats
+            .iter()
+            .map(|s| s.extension.chars().count())
+            .max()
+            .unwrap_or(0);
+        let count_width = stats
+            .iter()
+            .map(|s| s.files.to_string().len())
+            .max()
+            .unwrap_or(0);
+        for s in &stats {
+            writeln!(
+                out,
+                "{:<width$}  {:>count_width$}  {}",
+                s.extension,
+                s.files,
+                config.format_size(s.bytes),
+            )?;
+        }
+        return Ok(());
+    }
+
+    if config.format == OutputFormat::Porcelain {
+        let root_entry = TreeEntry::new(DirEntry::root(root, config)?, 0);
+        write_porcelain(&root_entry, root, config, out)?;
+        return Ok(());
+    }
+
+    if config.format == OutputFormat::Paths {
+        let root_entry = TreeEntry::new(DirEntry::root(root, config)?, 0);
+        write_paths(&root_entry, config, out)?;
+        return Ok(());
+    }
+
+    if config.format == OutputFormat::Jsonl {
+        let root_entry = TreeEntry::new(DirEntry::root(root, config)?, 0);
+        write_jsonl(&root_entry, root, config, out)?;
+        return Ok(());
+    }
+
+    if config.format == OutputFormat::Manifest {
+        let root_entry = TreeEntry::new(DirEntry::root(root, config)?, 0);
+        write_manifest(&root_entry, root, config, out)?;
+        return Ok(());
+    }
+
+    if config.format == OutputFormat::SummaryTree {
+        let root_entry = TreeEntry::new(DirEntry::root(root, config)?, 0);
+        let mut progress = Progress::for_terminal();
+        let node = summarize_dir(
+            &root_entry,
+            config,
+            &mut SeenLinks::new(config),
+            &mut progress,
+        );
+        progress.clear();
+        write_summary_tree(&node, true, "", config, out)?;
+        return Ok(());
+    }
+
+    if config.format == OutputFormat::Skeleton {
+        let root_entry = TreeEntry::new(DirEntry::root(root, config)?, 0);
+        write_skeleton(&root_entry, root, config, out)?;
+        return Ok(());
+    }
+
+    if config.format == OutputFormat::Json {
+        let node = build_tree_with_progress(root, config, &mut Progress::for_terminal())?;
+        utils::write_json(out, &json_tree(&node, root, config), config.pretty_json)?;
+        return Ok(());
+    }
+
+    if config.format == OutputFormat::Xml {
+        let node = build_tree_with_progress(root, config, &mut Progress::for_terminal())?;
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        write_xml(&node, config, out, 0)?;
+        return Ok(());
+    }
+
+    // 规范化的绝对根路径作为标题，避免保存的输出中只有含义不明的`.`
+    if config.print_root {
+        let canonical = fs::canonicalize(root)?;
+        writeln!(out, "{}", canonical.display())?;
+    }
+
+    let git_status = if config.show_git {
+        let status = load_git_status(root);
+        if status.is_none() {
+            logging::warn(format!(
+                "{}: not a git repository, --git ignored",
+                root.display()
+            ));
+        }
+        status
+    } else {
+        None
+    };
+
+    // 绝对路径或相对指定目录显示时只需解析一次根目录和基准目录
+    let absolute_root = match (config.path_display, &config.relative_to) {
+        (PathDisplay::Absolute, _) | (_, Some(_)) => Some(std::path::absolute(root)?),
+        _ => None,
+    };
+    let relative_base = match &config.relative_to {
+        Some(base) => Some(std::path::absolute(base)?),
+        None => None,
+    };
+
+    let filter_stats = FilterStats::default();
+    let mut ctx = RenderContext {
+        root,
+        absolute_root: absolute_root.as_deref(),
+        relative_base: relative_base.as_deref(),
+        config,
+        git_status: git_status.as_ref(),
+        out,
+        printed: 0,
+        truncated: false,
+        links: SeenLinks::new(config),
+        size_width: None,
+        filter_stats: &filter_stats,
+    };
+
+    let root_entry = TreeEntry::new(DirEntry::root(root, config)?, 0);
+    if config.show_size && config.align_sizes {
+        let mut progress = Progress::for_terminal();
+        ctx.size_width = Some(max_size_width(&root_entry, config, &mut progress));
+        progress.clear();
+    }
+    print_entry(&root_entry, true, "", &mut ctx)?;
+
+    if ctx.truncated {
+        logging::warn(format!("… output truncated at {} entries", ctx.printed));
+    }
+
+    if logging::enabled(Level::Verbose) {
+        report_filtering(&filter_stats, config);
+    }
+
+    Ok(())
+}
+
+/// 渲染过程中读取子条目时累计的过滤统计，`--verbose`时输出
+#[derive(Default)]
+struct FilterStats {
+    /// 检查过的条目数
+    examined: Cell<usize>,
+    /// 通过过滤的条目数
+    passed: Cell<usize>,
+    /// 按原因统计的被跳过条目数
+    skipped: RefCell<BTreeMap<&'static str, usize>>,
+}
+
+impl FilterStats {
+    /// 记录一个条目的过滤结果，`-vv`时逐条输出被跳过的条目及原因
+    fn record(&self, entry: &DirEntry, reason: Option<&'static str>) {
+        self.examined.set(self.examined.get() + 1);
+        match reason {
+            None => self.passed.set(self.passed.get() + 1),
+            Some(reason) => {
+                logging::debug(format!("skipped {}: {}", entry.path().display(), reason));
+                *self.skipped.borrow_mut().entry(reason).or_default() += 1;
+            }
+        }
+    }
+
+    /// 统计摘要，例如`examined 12 entries, 9 passed filters (hidden: 3)`
+    fn summary(&self) -> String {
+        let mut summary = format!(
+            "examined {} entries, {} passed filters",
+            self.examined.get(),
+            self.passed.get()
+        );
+        let skipped = self.skipped.borrow();
+        if !skipped.is_empty() {
+            let reasons: Vec<String> = skipped
+                .iter()
+                .map(|(reason, count)| format!("{}: {}", reason, count))
+                .collect();
+            summary.push_str(&format!(" ({})", reasons.join(", ")));
+        }
+        summary
+    }
+}
+
+/// 在`--verbose`模式下向stderr输出渲染过程中累计的过滤统计
+fn report_filtering(stats: &FilterStats, config: &Config) {
+    let max_depth = config
+        .max_depth
+        .map_or_else(|| "unlimited".to_string(), |depth| depth.to_string());
+    logging::info(format!(
+        "{}, sort: {:?}, max depth: {}",
+        stats.summary(),
+        config.sort_by,
+        max_depth
+    ));
+}
+
+/// 单次目录树渲染过程中共享的状态
+struct RenderContext<'a> {
+    /// 目录树的根路径
+    root: &'a Path,
+    /// `--absolute`模式下根目录的绝对路径
+    absolute_root: Option<&'a Path>,
+    /// `--relative-to`指定的基准目录（绝对路径）
+    relative_base: Option<&'a Path>,
+    /// 显示配置（权限、大小、日期等显示选项）
+    config: &'a Config,
+    /// 启用`--git`时的状态映射
+    git_status: Option<&'a GitStatusMap>,
+    /// 输出目标（标准输出或分页器）
+    out: &'a mut dyn Write,
+    /// 已打印的条目数
+    printed: usize,
+    /// 是否因达到`--max-files`上限而停止输出
+    truncated: bool,
+    /// `--dir-totals`累计大小时已计入的硬链接
+    links: SeenLinks,
+    /// 启用`--align-sizes`时大小列的宽度
+    size_width: Option<usize>,
+    /// 读取子条目时累计的过滤统计
+    filter_stats: &'a FilterStats,
+}
+
+/// 目录树节点，供以库方式使用时获取目录结构
+///
+/// 由`build_tree`生成，与文本输出共用同一套遍历、过滤和排序逻辑。
+#[derive(Debug)]
+pub struct TreeNode {
+    /// 文件或目录名称
+    pub name: String,
+    /// 完整路径
+    pub path: PathBuf,
+    /// 相对于树根的深度（根目录为0）
+    pub depth: usize,
+    /// 遍历时读取的元数据
+    pub metadata: NodeMetadata,
+    /// 经过过滤和排序的子节点
+    pub children: Vec<TreeNode>,
+}
+
+/// 按配置遍历目录并返回树形数据结构
+///
+/// 遵循与`execute`相同的过滤、排序和深度限制，但不产生任何输出。
+/// 整棵树会保存在内存中，超大目录请使用流式的`execute`。
+///
+/// # 参数
+/// * `root` - 根目录路径
+/// * `config` - 过滤、排序和深度配置
+///
+/// # 返回
+/// * `Ok(TreeNode)` 根节点
+/// * `Err(Error)` 根路径无法访问时返回
+pub fn build_tree(root: &Path, config: &Config) -> Result<TreeNode> {
+    build_tree_with_progress(root, config, &mut Progress::disabled())
+}
+
+/// `build_tree`的实现，遍历过程中更新进度显示
+fn build_tree_with_progress(
+    root: &Path,
+    config: &Config,
+    progress: &mut Progress,
+) -> Result<TreeNode> {
+    let root_entry = TreeEntry::new(DirEntry::root(root, config)?, 0);
+    let node = build_node(root_entry, config, progress);
+    progress.clear();
+    Ok(node)
+}
+
+/// 递归地将条目及其子条目转换为树节点
+fn build_node(entry: TreeEntry, config: &Config, progress: &mut Progress) -> TreeNode {
+    let children = if should_descend(&entry, config) {
+        read_children(&entry, config)
+            .map(|child| {
+                progress.tick();
+                build_node(child, config, progress)
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    TreeNode {
+        name: utils::escape_os_str(entry.entry.file_name()),
+        path: entry.entry.path().to_path_buf(),
+        depth: entry.depth,
+        metadata: entry.metadata,
+        children,
+    }
+}
+
+/// 将树节点递归输出为XML元素
+///
+/// 目录输出为`<directory>`，文件输出为`<file>`并带有`size`属性；
+/// `modified`和`permissions`属性仅在启用对应显示选项时输出。
+fn write_xml(node: &TreeNode, config: &Config, out: &mut dyn Write, level: usize) -> Result<()> {
+    let indent = "  ".repeat(level);
+    let tag = if node.metadata.is_dir {
+        "directory"
+    } else {
+        "file"
+    };
+
+    let mut attributes = format!(r#" name="{}""#, utils::xml_escape(&node.name));
+    if !node.metadata.is_dir {
+        if let Some(size) = node.metadata.size {
+            attributes.push_str(&format!(r#" size="{}""#, size));
+        }
+    }
+    if config.show_date {
+        if let Some(modified) = node.metadata.modified {
+            attributes.push_str(&format!(r#" modified="{}""#, utils::format_time(modified)));
+        }
+    }
+    if config.show_permissions {
+        if let Some(permissions) = &node.metadata.permissions {
+            attributes.push_str(&format!(
+                r#" permissions="{}""#,
+                utils::xml_escape(permissions)
+            ));
+        }
+    }
+
+    if node.children.is_empty() {
+        writeln!(out, "{}<{}{}/>", indent, tag, attributes)?;
+        return Ok(());
+    }
+
+    writeln!(out, "{}<{}{}>", indent, tag, attributes)?;
+    for child in &node.children {
+        write_xml(child, config, out, level + 1)?;
+    }
+    writeln!(out, "{}</{}>", indent, tag)?;
+    Ok(())
+}
+
+/// 以`--porcelain`格式逐行输出条目及其子条目
+///
+/// 每行为制表符分隔的`类型 深度 大小 修改时间 路径`：
+/// 类型为`d`（目录）、`f`（文件）或`l`（符号链接）；大小为字节数，目录为`-`；
+/// 修改时间为Unix时间戳（秒），无法读取时为`-`；路径相对于根目录，根目录为`.`。
+/// 路径中的制表符和换行符转义为`\t`、`\n`，保证每个条目恰好一行。
+/// 遵循过滤、排序和深度设置，但忽略颜色等外观选项。
+fn write_porcelain(
+    entry: &TreeEntry,
+    root: &Path,
+    config: &Config,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let metadata = &entry.metadata;
+    let kind = if metadata.is_symlink {
+        'l'
+    } else if metadata.is_dir {
+        'd'
+    } else {
+        'f'
+    };
+    let size = match metadata.size {
+        Some(size) if !metadata.is_dir => size.to_string(),
+        _ => "-".to_string(),
+    };
+    let mtime = metadata
+        .modified
+        .and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map_or_else(|| "-".to_string(), |d| d.as_secs().to_string());
+    let relative = entry
+        .entry
+        .path()
+        .strip_prefix(root)
+        .unwrap_or(Path::new(""));
+    let path = if relative.as_os_str().is_empty() {
+        ".".to_string()
+    } else {
+        utils::escape_os_str(relative.as_os_str())
+            .replace('\t', "\\t")
+            .replace('\n', "\\n")
+    };
+
+    writeln!(
+        out,
+        "{}\t{}\t{}\t{}\t{}",
+        kind, entry.depth, size, mtime, path
+    )?;
+
+    if should_descend(entry, config) {
+        for child in read_children(entry, config) {
+            write_porcelain(&child, root, config, out)?;
+        }
+    }
+    Ok(())
+}
+
+/// JSON输出中相对根目录的路径，根目录为`.`
+fn json_relative_path(path: &Path, root: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(Path::new(""));
+    if relative.as_os_str().is_empty() {
+        ".".to_string()
+    } else {
+        utils::escape_os_str(relative.as_os_str())
     }
 }
 
-/// Helper extension trait for DirEntry to create entries from paths
-trait DirEntryExt {
-    /// 从路径创建DirEntry条目
-    fn from_path(path: &Path) -> Result<DirEntry>;
-}
+/// 生成`--format json`和`--format jsonl`共用的条目字段
+///
+/// 包含`name`、`path`、`depth`和`type`（`dir`、`file`或`symlink`），
+/// 启用对应显示选项时附带`size`（字节数）、`modified`（Unix时间戳，秒）和`permissions`。
+fn json_entry(
+    name: String,
+    path: &Path,
+    depth: usize,
+    metadata: &NodeMetadata,
+    root: &Path,
+    config: &Config,
+) -> serde_json::Value {
+    let kind = if metadata.is_symlink {
+        "symlink"
+    } else if metadata.is_dir {
+        "dir"
+    } else {
+        "file"
+    };
+
+    let mut object = serde_json::json!({
+        "name": name,
+        "path": json_relative_path(path, root),
+        "depth": depth,
+        "type": kind,
+    });
+    if config.show_size && !metadata.is_dir {
+        object["size"] = metadata.size.into();
+    }
+    if config.show_date {
+        object["modified"] = metadata
+            .modified
+            .and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+            .into();
+    }
+    if config.show_permissions {
+        object["permissions"] = metadata.permissions.clone().into();
+    }
+    object
+}
+
+/// 将树节点转换为嵌套的JSON对象，目录附带`children`数组
+fn json_tree(node: &TreeNode, root: &Path, config: &Config) -> serde_json::Value {
+    let mut object = json_entry(
+        node.name.clone(),
+        &node.path,
+        node.depth,
+        &node.metadata,
+        root,
+        config,
+    );
+    if node.metadata.is_dir {
+        object["children"] = node
+            .children
+            .iter()
+            .map(|child| json_tree(child, root, config))
+            .collect();
+    }
+    object
+}
+
+/// 以`--format jsonl`格式逐行输出条目及其子条目，每行是一个独立的JSON对象
+///
+/// 对象的字段见[`json_entry`]，另有`parent`（父条目的`path`，根目录为`null`）。
+/// 条目在遍历时立即输出，内存占用与目录树大小无关。
+fn write_jsonl(entry: &TreeEntry, root: &Path, config: &Config, out: &mut dyn Write) -> Result<()> {
+    let path = entry.entry.path();
+    let mut object = json_entry(
+        utils::escape_os_str(entry.entry.file_name()),
+        path,
+        entry.depth,
+        &entry.metadata,
+        root,
+        config,
+    );
+    object["parent"] = if entry.depth == 0 {
+        serde_json::Value::Null
+    } else {
+        path.parent()
+            .map(|parent| json_relative_path(parent, root))
+            .into()
+    };
+    writeln!(out, "{}", object)?;
+
+    if should_descend(entry, config) {
+        for child in read_children(entry, config) {
+            write_jsonl(&child, root, config, out)?;
+        }
+    }
+    Ok(())
+}
+
+/// 以`--only-depth`格式输出恰好位于`depth`层的条目，每行一个相对根目录的路径
+///
+/// 只遍历到目标层为止，不受`--max-depth`影响；根目录输出为`.`。
+fn write_depth_slice(
+    entry: &TreeEntry,
+    root: &Path,
+    depth: usize,
+    config: &Config,
+    out: &mut dyn Write,
+) -> Result<()> {
+    if entry.depth == depth {
+        let relative = entry
+            .entry
+            .path()
+            .strip_prefix(root)
+            .unwrap_or(Path::new(""));
+        if relative.as_os_str().is_empty() {
+            writeln!(out, ".")?;
+        } else {
+            writeln!(out, "{}", utils::escape_os_str(relative.as_os_str()))?;
+        }
+        return Ok(());
+    }
+
+    if entry.is_dir() {
+        for child in read_children(entry, config) {
+            write_depth_slice(&child, root, depth, config, out)?;
+        }
+    }
+    Ok(())
+}
+
+/// 以`--find-dirs`格式输出名称匹配`pattern`的目录，每行一个以根路径开头的完整路径
+///
+/// 匹配的目录仍会继续向下查找；遍历深度只受`--max-depth`限制，未指定时搜索整个子树。
+fn write_found_dirs(
+    entry: &TreeEntry,
+    pattern: &str,
+    config: &Config,
+    out: &mut dyn Write,
+) -> Result<()> {
+    if entry.depth > 0 && entry.is_dir() && utils::glob_match(pattern, entry.entry.file_name()) {
+        out.write_all(entry.entry.path().as_os_str().as_encoded_bytes())?;
+        writeln!(out)?;
+    }
+
+    if should_descend(entry, config) {
+        for child in read_children(entry, config) {
+            write_found_dirs(&child, pattern, config, out)?;
+        }
+    }
+    Ok(())
+}
+
+/// 以`--format paths`格式输出条目及其子条目的路径
+///
+/// 与`find`相同，路径以根路径开头（包括根路径本身），按原始字节输出不做转义。
+/// 启用`-0`时每个路径以NUL结尾，否则以换行结尾。
+fn write_paths(entry: &TreeEntry, config: &Config, out: &mut dyn Write) -> Result<()> {
+    out.write_all(entry.entry.path().as_os_str().as_encoded_bytes())?;
+    out.write_all(if config.null_separated { b"\0" } else { b"\n" })?;
+
+    if should_descend(entry, config) {
+        for child in read_children(entry, config) {
+            write_paths(&child, config, out)?;
+        }
+    }
+    Ok(())
+}
+
+/// 以`--manifest`格式输出条目下所有普通文件的SHA-256摘要
+///
+/// 每行为`摘要  路径`（与`sha256sum`相同），路径相对于根目录并统一使用`/`分隔，
+/// 便于比较不同系统上的清单。目录和符号链接不输出；
+/// 无法读取的文件输出`!  路径`并在stderr给出原因，不中断遍历；
+/// 超过`--skip-larger-than`的文件不计算摘要，输出`(skipped, too large)  路径`。
+fn write_manifest(
+    entry: &TreeEntry,
+    root: &Path,
+    config: &Config,
+    out: &mut dyn Write,
+) -> Result<()> {
+    if !entry.is_dir() && !entry.metadata.is_symlink {
+        let path = entry.entry.path();
+        let relative = path
+            .strip_prefix(root)
+            .ok()
+            .filter(|relative| !relative.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new(entry.entry.file_name()));
+        let label = relative
+            .components()
+            .map(|component| utils::escape_os_str(component.as_os_str()))
+            .collect::<Vec<_>>()
+            .join("/")
+            .replace('\n', "\\n");
+
+        if too_large(&entry.metadata, config.skip_larger_than) {
+            writeln!(out, "(skipped, too large)  {}", label)?;
+        } else {
+            match utils::sha256_file(path) {
+                Ok(digest) => writeln!(out, "{}  {}", digest, label)?,
+                Err(err) => {
+                    logging::warn(format!("{}: {}", path.display(), err));
+                    writeln!(out, "!  {}", label)?;
+                }
+            }
+        }
+    }
+
+    if should_descend(entry, config) {
+        for child in read_children(entry, config) {
+            write_manifest(&child, root, config, out)?;
+        }
+    }
+    Ok(())
+}
+
+/// 以`--format skeleton`格式输出目录结构，用于在其他位置重建空的目录结构
+///
+/// 每行一个相对根目录的路径，统一使用`/`分隔，目录以`/`结尾；根目录本身不输出。
+/// 按先序遍历输出，父目录总在其子条目之前。
+fn write_skeleton(
+    entry: &TreeEntry,
+    root: &Path,
+    config: &Config,
+    out: &mut dyn Write,
+) -> Result<()> {
+    if entry.depth > 0 {
+        let relative = entry
+            .entry
+            .path()
+            .strip_prefix(root)
+            .unwrap_or(Path::new(entry.entry.file_name()));
+        let label = relative
+            .components()
+            .map(|component| utils::escape_os_str(component.as_os_str()))
+            .collect::<Vec<_>>()
+            .join("/")
+            .replace('\n', "\\n");
+        let suffix = if entry.is_dir() { "/" } else { "" };
+        writeln!(out, "{}{}", label, suffix)?;
+    }
+
+    if should_descend(entry, config) {
+        for child in read_children(entry, config) {
+            write_skeleton(&child, root, config, out)?;
+        }
+    }
+    Ok(())
+}
+
+/// `--summary-tree`中的一个目录节点
+struct DirSummary {
+    /// 目录名称
+    name: String,
+    /// 相对于树根的深度（根目录为0）
+    depth: usize,
+    /// 整个子树的统计（不含目录本身）
+    summary: TreeSummary,
+    /// 深度限制内的子目录
+    children: Vec<DirSummary>,
+}
+
+/// 统计目录的整个子树，只保留深度限制内的子目录节点
+///
+/// 与`du --max-depth`相同，文件数和大小包含深度限制以下的全部内容，
+/// 深度限制只决定输出哪些目录。根路径为文件时统计该文件本身。
+fn summarize_dir(
+    entry: &TreeEntry,
+    config: &Config,
+    links: &mut SeenLinks,
+    progress: &mut Progress,
+) -> DirSummary {
+    let mut summary = TreeSummary::default();
+    let mut children = Vec::new();
+
+    if entry.is_dir() {
+        for child in read_children(entry, config) {
+            progress.tick();
+            summary.add(&child.metadata, links);
+            if child.is_dir() {
+                let node = summarize_dir(&child, config, links, progress);
+                summary.merge(&node.summary);
+                if should_descend(entry, config) {
+                    children.push(node);
+                }
+            }
+        }
+    } else {
+        summary.add(&entry.metadata, links);
+    }
+
+    DirSummary {
+        name: utils::escape_os_str(entry.entry.file_name()),
+        depth: entry.depth,
+        summary,
+        children,
+    }
+}
+
+/// 以`--summary-tree`格式输出目录节点：每个目录一行，附带文件数和总大小，不输出文件
+fn write_summary_tree(
+    node: &DirSummary,
+    is_last: bool,
+    prefix: &str,
+    config: &Config,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let branch = match (node.depth, is_last) {
+        (0, _) => "",
+        (_, true) => "└── ",
+        (_, false) => "├── ",
+    };
+    let files = node.summary.files;
+    writeln!(
+        out,
+        "{}{}{} ({} {}, {})",
+        prefix,
+        branch,
+        utils::paint(&node.name, config.theme.directory, config.color),
+        files,
+        if files == 1 { "file" } else { "files" },
+        utils::paint(
+            &config.format_size(node.summary.bytes),
+            config.theme.size,
+            config.color
+        )
+    )?;
+
+    let child_prefix = match (node.depth, is_last) {
+        (0, _) => String::new(),
+        (_, true) => format!("{}    ", prefix),
+        (_, false) => format!("{}│   ", prefix),
+    };
+    for (idx, child) in node.children.iter().enumerate() {
+        let is_last_child = idx + 1 == node.children.len();
+        write_summary_tree(child, is_last_child, &child_prefix, config, out)?;
+    }
+    Ok(())
+}
+
+/// 计算将要显示的文件中格式化后大小的最大宽度，用于`--align-sizes`
+///
+/// 按与树形输出相同的过滤和深度限制预先遍历一次。
+fn max_size_width(entry: &TreeEntry, config: &Config, progress: &mut Progress) -> usize {
+    let own = match entry.metadata.size {
+        Some(size) if !entry.metadata.is_dir => config.format_size(size).len(),
+        _ => 0,
+    };
+    if !should_descend(entry, config) {
+        return own;
+    }
+    read_children(entry, config)
+        .map(|child| {
+            progress.tick();
+            max_size_width(&child, config, progress)
+        })
+        .fold(own, usize::max)
+}
+
+/// 判断是否需要继续读取目录的子条目（是目录且未超过最大深度）
+fn should_descend(entry: &TreeEntry, config: &Config) -> bool {
+    entry.is_dir() && config.max_depth.is_none_or(|max| entry.depth < max)
+}
+
+/// 条目的元数据快照，遍历时只读取一次
+///
+/// 排序、过滤和各种输出格式都复用这些值，不会重复stat文件。
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeMetadata {
+    /// 文件大小（字节），无法读取时为None
+    pub size: Option<u64>,
+    /// 最后修改时间，无法读取时为None
+    pub modified: Option<SystemTime>,
+    /// 权限字符串（例如"rwxr--r--"），无法读取时为None
+    pub permissions: Option<String>,
+    /// 是否为目录
+    pub is_dir: bool,
+    /// 是否为符号链接（不跟随链接判断）
+    pub is_symlink: bool,
+    /// 是否为可执行文件
+    pub is_executable: bool,
+    /// 有多个硬链接的文件的（设备号, inode号），用于`--count-links-once`；
+    /// 只有一个链接或非Unix系统时为None
+    pub link_id: Option<(u64, u64)>,
+}
+
+impl NodeMetadata {
+    /// 元数据无法读取时使用的占位值
+    pub fn unknown() -> Self {
+        Self {
+            size: None,
+            modified: None,
+            permissions: None,
+            is_dir: false,
+            is_symlink: false,
+            is_executable: false,
+            link_id: None,
+        }
+    }
+
+    /// 从目录条目读取元数据
+    ///
+    /// 条目类型来自目录读取结果，始终可用；其余字段在stat失败时为None。
+    /// 可执行判断见[`utils::is_executable`]。
+    pub fn from_entry(entry: &DirEntry) -> Self {
+        let file_type = entry.file_type();
+        let is_dir = file_type.is_dir();
+        let is_symlink = file_type.is_symlink();
+
+        let Ok(metadata) = entry.metadata() else {
+            return Self {
+                is_dir,
+                is_symlink,
+                ..Self::unknown()
+            };
+        };
+
+        let is_executable = !is_dir && utils::is_executable(entry.path(), &metadata);
+
+        #[cfg(unix)]
+        let link_id = {
+            use std::os::unix::fs::MetadataExt;
+            (!is_dir && metadata.nlink() > 1).then(|| (metadata.dev(), metadata.ino()))
+        };
+        #[cfg(not(unix))]
+        let link_id = None;
+
+        Self {
+            size: Some(metadata.len()),
+            modified: metadata.modified().ok(),
+            permissions: Some(utils::format_permissions(&metadata)),
+            is_dir,
+            is_symlink,
+            is_executable,
+            link_id,
+        }
+    }
+}
+
+/// 目录条目及其缓存的元数据
+///
+/// 元数据在读取目录时只获取一次，排序和渲染都复用该值，
+/// 避免按大小/日期排序时每次比较都重新stat文件。
+struct TreeEntry {
+    /// walkdir目录条目
+    entry: DirEntry,
+    /// 相对于树根的深度（根目录为0）
+    depth: usize,
+    /// 缓存的元数据
+    metadata: NodeMetadata,
+}
+
+impl TreeEntry {
+    /// 创建条目并读取一次元数据
+    fn new(entry: DirEntry, depth: usize) -> Self {
+        let metadata = NodeMetadata::from_entry(&entry);
+        Self {
+            entry,
+            depth,
+            metadata,
+        }
+    }
+
+    /// 是否为目录
+    fn is_dir(&self) -> bool {
+        self.metadata.is_dir
+    }
+
+    /// `--classify`模式下追加在名称后的类型标识
+    ///
+    /// 目录为`/`，可执行文件为`*`，符号链接为`@`
+    fn classify_suffix(&self) -> &'static str {
+        if self.metadata.is_symlink {
+            "@"
+        } else if self.metadata.is_dir {
+            "/"
+        } else if self.metadata.is_executable {
+            "*"
+        } else {
+            ""
+        }
+    }
+
+    /// 文件大小，元数据不可用时为0
+    fn size(&self) -> u64 {
+        self.metadata.size.unwrap_or(0)
+    }
 
-/// Implementation of the DirEntryExt trait for DirEntry
-impl DirEntryExt for DirEntry {
-    fn from_path(path: &Path) -> Result<DirEntry> {
-        Ok(WalkDir::new(path).into_iter().next().unwrap()?)
+    /// 修改时间，元数据不可用时为UNIX纪元
+    fn modified(&self) -> SystemTime {
+        self.metadata.modified.unwrap_or(SystemTime::UNIX_EPOCH)
     }
 }
 
-/// 使用给定的参数和配置执行tree命令
-///
-/// # 参数
-/// * `args` - 包含路径、最大深度、过滤模式等参数的TreeArgs结构体
-/// * `config` - 包含显示选项、排序方式、过滤条件等运行时配置
+/// 读取目录的直接子条目，应用过滤条件并排序
 ///
-/// # 返回
-/// * `Ok(())` 命令执行成功时返回
-/// * `Err(Error)` 执行过程中发生错误时返回
-pub fn execute(args: &TreeArgs, config: &Config) -> Result<()> {
-    let root = &args.path;
-    let walker = WalkDir::new(root).max_depth(config.max_depth.unwrap_or(std::usize::MAX));
+/// `SortBy::Name`和`SortBy::None`（未启用`--dirs-first`和`--reverse`时）直接使用WalkDir的顺序，
+/// 子条目按需逐个读取；其他排序方式需要先缓冲该目录的全部子条目。
+/// 不跟随符号链接，无法读取的条目会被跳过。
+fn read_children<'a>(
+    dir: &TreeEntry,
+    config: &'a Config,
+) -> Box<dyn Iterator<Item = TreeEntry> + 'a> {
+    read_children_counted(dir, config, None)
+}
 
-    let mut entries: Vec<DirEntry> = walker
+/// 与[`read_children`]相同，同时将每个子条目的过滤结果记入`stats`
+fn read_children_counted<'a>(
+    dir: &TreeEntry,
+    config: &'a Config,
+    stats: Option<&'a FilterStats>,
+) -> Box<dyn Iterator<Item = TreeEntry> + 'a> {
+    let depth = dir.depth + 1;
+    let mut walker = WalkDir::new(dir.entry.path()).min_depth(1).max_depth(1);
+    if config.sort_by != SortBy::None {
+        // 先按名称排序，其他排序方式在此基础上稳定排序
+        walker = walker.sort_by_file_name();
+    }
+    let children = walker
         .into_iter()
-        .filter_entry(|e| filter_entry(e, &config))
-        .filter_map(|e| e.ok())
-        .collect();
-
-    sort_entries(&mut entries, &config);
+        .filter_map(|e| match e {
+            Ok(entry) => Some(entry),
+            Err(err) => {
+                logging::warn(format!("skipping: {}", Error::from(err)));
+                None
+            }
+        })
+        .map(move |e| TreeEntry::new(e, depth))
+        .filter(move |child| {
+            let reason = skip_reason(&child.entry, &child.metadata, config);
+            if let Some(stats) = stats {
+                stats.record(&child.entry, reason);
+            }
+            reason.is_none()
+        });
 
-    for (index, entry) in entries.iter().enumerate() {
-        let is_last = index == entries.len() - 1;
-        print_entry(entry, root, is_last, "", &config)?;
+    if matches!(config.sort_by, SortBy::Name | SortBy::None)
+        && !config.dirs_first
+        && !config.reverse
+    {
+        return Box::new(children);
     }
 
-    Ok(())
+    let mut children: Vec<TreeEntry> = children.collect();
+    sort_entries(&mut children, config);
+    if config.dirs_first {
+        // 稳定排序，目录和文件各自保持上一步的顺序
+        children.sort_by_key(|child| !child.is_dir());
+    }
+    Box::new(children.into_iter())
 }
 
 /// 判断目录条目是否应包含在输出中
 ///
+/// 过滤规则见[`skip_reason`]
+fn filter_entry(entry: &DirEntry, metadata: &NodeMetadata, config: &Config) -> bool {
+    skip_reason(entry, metadata, config).is_none()
+}
+
+/// 返回条目被过滤掉的原因，条目应保留时返回`None`
+///
 /// 过滤逻辑包含：
-/// 1. 根据配置隐藏/显示隐藏文件（以点开头的文件）
-/// 2. 按文件名模式过滤（当配置包含pattern时）
+/// 1. 排除默认忽略的目录（`.git`、`node_modules`等，同名的普通文件不受影响）和`--exclude-from`中的模式，不再深入遍历
+/// 2. 根据配置隐藏/显示隐藏文件（以点开头的文件）
+/// 3. 按文件名模式过滤（当配置包含pattern时）
+/// 4. 按文件大小范围过滤（目录不受影响）
+/// 5. 按扩展名过滤（目录不受影响）
+/// 6. 按修改时间范围过滤（目录不受影响）
 ///
 /// # 参数
 /// * `entry` - 要检查的目录条目
+/// * `metadata` - 条目缓存的元数据
 /// * `config` - 包含过滤设置的配置项（show_hidden、pattern等）
-fn filter_entry(entry: &DirEntry, config: &Config) -> bool {
+fn skip_reason(entry: &DirEntry, metadata: &NodeMetadata, config: &Config) -> Option<&'static str> {
+    if metadata.is_dir
+        && !config.excludes.is_empty()
+        && entry
+            .file_name()
+            .to_str()
+            .is_some_and(|name| config.excludes.contains(name))
+    {
+        return Some("default exclude");
+    }
+
+    let pattern_excluded =
+        config
+            .exclude_patterns
+            .iter()
+            .any(|pattern| match pattern.strip_suffix('/') {
+                Some(dir_pattern) => {
+                    metadata.is_dir && utils::glob_match(dir_pattern, entry.file_name())
+                }
+                None => utils::glob_match(pattern, entry.file_name()),
+            });
+    if pattern_excluded {
+        return Some("exclude pattern");
+    }
+
     // 优先检查目录类型过滤条件
     if config.directories_only {
-        if !entry.file_type().is_dir() {
-            return false;
-        }
         // 当启用目录过滤时，直接保留目录项
-        return true;
+        return (!metadata.is_dir).then_some("not a directory");
     }
 
     if !config.show_hidden && utils::is_hidden(entry.path()) {
-        return false;
+        return Some("hidden");
     }
 
-    let pattern_match = if let Some(ref pattern) = config.pattern {
-        entry
-            .file_name()
-            .to_str()
-            .map(|s| s.contains(pattern))
-            .unwrap_or(false)
-    } else {
-        true
-    };
+    let pattern_match = config
+        .pattern
+        .as_ref()
+        .is_none_or(|pattern| utils::os_str_contains(entry.file_name(), pattern));
 
     if !pattern_match {
-        return false;
+        return Some("pattern mismatch");
+    }
+
+    // 按文件大小过滤，目录作为容器始终保留
+    if !metadata.is_dir && (config.min_size.is_some() || config.max_size.is_some()) {
+        let in_range = metadata.size.is_some_and(|size| {
+            config.min_size.is_none_or(|min| size >= min)
+                && config.max_size.is_none_or(|max| size <= max)
+        });
+        if !in_range {
+            return Some("size out of range");
+        }
+    }
+
+    // 按扩展名过滤，目录始终保留以维持结构
+    if !metadata.is_dir && !config.extensions.is_empty() {
+        let matches = entry
+            .path()
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| config.extensions.contains(&ext.to_lowercase()));
+        if !matches {
+            return Some("extension mismatch");
+        }
+    }
+
+    // 按修改时间过滤，目录同样始终保留
+    if !metadata.is_dir && (config.newer_than.is_some() || config.older_than.is_some()) {
+        let in_range = metadata.modified.is_some_and(|modified| {
+            config.newer_than.is_none_or(|bound| modified >= bound)
+                && config.older_than.is_none_or(|bound| modified <= bound)
+        });
+        if !in_range {
+            return Some("modification time out of range");
+        }
     }
 
-    true
+    None
 }
 
 /// 根据配置对目录条目进行排序
 ///
+/// 排序键来自条目缓存的元数据，不会产生额外的stat调用。
+///
 /// # 排序规则
 /// - Type: 目录优先排序
 /// - Size: 按文件大小升序排列
@@ -112,94 +1675,206 @@ fn filter_entry(entry: &DirEntry, config: &Config) -> bool {
 /// - Name: 保持文件系统默认顺序
 ///
 /// # 参数
-/// * `entries` - 待排序的目录条目向量
+/// * `entries` - 待排序的目录条目
 /// * `config` - 包含排序枚举(SortBy)的配置项
-fn sort_entries(entries: &mut Vec<DirEntry>, config: &Config) {
+fn sort_entries(entries: &mut [TreeEntry], config: &Config) {
     match config.sort_by {
-        SortBy::Type => entries.sort_by_key(|a| !a.file_type().is_dir()),
-        SortBy::Size => entries.sort_by_key(|a| a.metadata().map(|m| m.len()).unwrap_or(0)),
-        SortBy::Date => entries.sort_by_key(|a| {
-            a.metadata()
-                .ok()
-                .and_then(|m| m.modified().ok())
-                .unwrap_or(SystemTime::UNIX_EPOCH)
+        SortBy::Type => entries.sort_by_key(|a| !a.is_dir()),
+        // 大小或时间相同时按名称排序，保证输出在不同平台上一致
+        SortBy::Size => entries.sort_by(|a, b| {
+            a.size()
+                .cmp(&b.size())
+                .then_with(|| a.entry.file_name().cmp(b.entry.file_name()))
         }),
-        SortBy::Name => {} // Default sorting by name is already done by the file system
+        SortBy::Date => entries.sort_by(|a, b| {
+            a.modified()
+                .cmp(&b.modified())
+                .then_with(|| a.entry.file_name().cmp(b.entry.file_name()))
+        }),
+        SortBy::Name => entries.sort_by(|a, b| a.entry.file_name().cmp(b.entry.file_name())),
+        SortBy::None => return,
+    }
+    if config.reverse {
+        entries.reverse();
+    }
+}
+
+/// 根据`PathDisplay`生成条目显示的名称或路径
+///
+/// 根目录在相对路径模式下显示为用户给出的路径。
+fn entry_label(entry: &TreeEntry, ctx: &RenderContext) -> String {
+    let path = entry.entry.path();
+    let relative = path.strip_prefix(ctx.root).unwrap_or(path);
+
+    // 相对于指定目录显示，无法计算相对路径时（如不同盘符）退回绝对路径
+    if let (Some(base), Some(absolute_root)) = (ctx.relative_base, ctx.absolute_root) {
+        let absolute = if entry.depth > 0 {
+            absolute_root.join(relative)
+        } else {
+            absolute_root.to_path_buf()
+        };
+        return match utils::relative_path(&absolute, base) {
+            Some(path) if path.as_os_str().is_empty() => ".".to_string(),
+            Some(path) => utils::escape_os_str(path.as_os_str()),
+            None => utils::escape_os_str(absolute.as_os_str()),
+        };
+    }
+
+    match (ctx.config.path_display, ctx.absolute_root) {
+        (PathDisplay::Absolute, Some(absolute_root)) if entry.depth > 0 => {
+            utils::escape_os_str(absolute_root.join(relative).as_os_str())
+        }
+        (PathDisplay::Absolute, Some(absolute_root)) => {
+            utils::escape_os_str(absolute_root.as_os_str())
+        }
+        (PathDisplay::Relative, _) if entry.depth > 0 => utils::escape_os_str(relative.as_os_str()),
+        (PathDisplay::Relative, _) => utils::escape_os_str(ctx.root.as_os_str()),
+        _ => utils::escape_os_str(entry.entry.file_name()),
     }
 }
 
 /// 以带格式的方式打印目录条目及其元数据
 ///
-/// 递归生成子目录时会重新应用：
+/// 目录会继续读取子条目，并对其应用：
 /// 1. 过滤条件（filter_entry）
 /// 2. 排序规则（sort_entries）
 /// 3. 当前配置的显示选项
 ///
+/// 打印的条目数达到`--max-files`上限后停止输出。
+/// 启用`--dir-totals`时，在有子条目的目录末尾输出一行累计大小。
+///
 /// # 参数
 /// * `entry` - 要打印的目录条目
-/// * `root` - 目录树的根路径
 /// * `is_last` - 当前条目是否为父目录的最后一个子项
 /// * `prefix` - 用于构建树状缩进的前缀字符串
-/// * `config` - 显示配置（权限、大小、日期等显示选项）
+/// * `ctx` - 渲染状态（根路径、显示配置、已打印计数等）
 ///
 /// # 返回值
-/// 打印成功返回`Ok(())`，访问元数据出错时返回`Err(Error)`
+/// 打印成功返回该条目（目录为其已输出的全部子条目）的文件总字节数
 fn print_entry(
-    entry: &DirEntry,
-    root: &Path,
+    entry: &TreeEntry,
     is_last: bool,
     prefix: &str,
-    config: &Config,
-) -> Result<()> {
-    let file_name = entry.file_name().to_string_lossy();
-    let depth = entry.depth();
+    ctx: &mut RenderContext,
+) -> Result<u64> {
+    let config = ctx.config;
+    if config.max_files.is_some_and(|max| ctx.printed >= max) {
+        ctx.truncated = true;
+        return Ok(0);
+    }
+
+    let mut label = entry_label(entry, ctx);
+    // 过滤已在读取子条目时完成，这里只截断显示的名称
+    if let Some(width) = config.max_name_width {
+        label = utils::truncate_middle(&label, width);
+    }
 
-    let new_prefix = if depth == 0 {
+    let mut line = if entry.depth == 0 {
         String::new()
     } else if is_last {
         format!("{}└── ", prefix)
     } else {
         format!("{}├── ", prefix)
     };
+    line.push_str(&label);
+    if config.classify {
+        line.push_str(entry.classify_suffix());
+    }
+    // --child-count需要在输出目录行之前知道子条目数，此时一次性读取子条目，展开时复用
+    let stats = Some(ctx.filter_stats);
+    let buffered: Option<Vec<TreeEntry>> = (config.child_count && entry.is_dir())
+        .then(|| read_children_counted(entry, config, stats).collect());
+    if let Some(children) = &buffered {
+        line.push_str(&format!(" ({})", children.len()));
+    }
 
-    // 确保前缀长度与深度严格匹配
-    let expected_prefix_len = depth * 4;
-    let new_prefix = if new_prefix.len() < expected_prefix_len {
-        let padding = " ".repeat(expected_prefix_len - new_prefix.len());
-        format!("{}{}", new_prefix, padding)
-    } else {
-        new_prefix
-    };
-
-    let mut line = new_prefix.clone();
-    line.push_str(&file_name);
+    let age_color = config
+        .age_colors
+        .then_some(&entry.metadata)
+        .filter(|metadata| !metadata.is_dir)
+        .and_then(|metadata| metadata.modified)
+        .map(utils::age_color);
+    if let Some(color) = age_color {
+        line = utils::styled(&line, Some(color), false, config.color);
+    } else if config.depth_colors {
+        let tint = Some(utils::depth_color(entry.depth));
+        line = utils::styled(&line, tint, entry.is_dir(), config.color);
+    } else if entry.is_dir() {
+        line = utils::paint(&line, config.theme.directory, config.color);
+    } else if entry.metadata.is_executable {
+        line = utils::paint(&line, config.theme.executable, config.color);
+    } else if utils::is_source_name(&label) {
+        line = utils::paint(&line, config.theme.source, config.color);
+    }
 
-    if entry.file_type().is_dir() {
-        line = line.blue().to_string();
+    let metadata = &entry.metadata;
+    let size_text = metadata
+        .size
+        .filter(|_| config.show_size && !metadata.is_dir)
+        .map(|size| config.format_size(size));
+    if let Some(width) = ctx.size_width {
+        // 右对齐的固定宽度大小列，目录留空
+        let column = format!("{:>width$}", size_text.unwrap_or_default());
+        line = format!(
+            "{} {}",
+            utils::paint(&column, config.theme.size, config.color),
+            line
+        );
+    } else if let Some(size_text) = size_text {
+        line = format!(
+            "{} {}",
+            line,
+            utils::paint(&size_text, config.theme.size, config.color)
+        );
     }
 
-    if let Ok(metadata) = entry.metadata() {
-        if config.show_permissions {
-            line = format!("{} {}", utils::format_permissions(&metadata), line);
+    if config.show_permissions {
+        if let Some(permissions) = &metadata.permissions {
+            line = format!("{} {}", permissions, line);
         }
+    }
 
-        if config.show_size && !entry.file_type().is_dir() {
-            let size = metadata.len();
-            line = format!("{} {}", line, format_size(size, BINARY).green());
+    if config.show_date {
+        if let Some(time) = metadata.modified {
+            let formatted_time = if config.relative_dates {
+                utils::format_relative_time(time)
+            } else {
+                utils::format_time(time)
+            };
+            line = format!(
+                "{} {}",
+                line,
+                utils::paint(&formatted_time, config.theme.date, config.color)
+            );
         }
+    }
 
-        if config.show_date {
-            if let Ok(time) = metadata.modified() {
-                let formatted_time = utils::format_time(time);
-                line = format!("{} {}", line, formatted_time.yellow());
-            }
-        }
+    if let Some(status) = ctx.git_status.and_then(|map| {
+        entry
+            .entry
+            .path()
+            .strip_prefix(ctx.root)
+            .ok()
+            .and_then(|relative| map.get(relative))
+    }) {
+        let marker = match status.as_str() {
+            "??" => utils::styled(status, Some(Color::Red), false, config.color),
+            "!!" => utils::dimmed(status, config.color),
+            _ => utils::styled(status, Some(Color::Yellow), false, config.color),
+        };
+        line = format!("{} [{}]", line, marker);
     }
 
-    println!("{}", line);
+    writeln!(ctx.out, "{}", line)?;
+    ctx.printed += 1;
+
+    if !entry.is_dir() {
+        return Ok(ctx.links.size(metadata));
+    }
 
-    if entry.file_type().is_dir() {
-        let new_prefix = if depth == 0 {
+    let mut total = 0;
+    if should_descend(entry, config) {
+        let child_prefix = if entry.depth == 0 {
             String::new()
         } else if is_last {
             format!("{}    ", prefix)
@@ -207,43 +1882,1037 @@ fn print_entry(
             format!("{}│   ", prefix)
         };
 
-        // 确保子目录前缀长度与深度严格匹配
-        let expected_prefix_len = (depth + 1) * 4;
-        let new_prefix = if new_prefix.len() < expected_prefix_len {
-            let padding = " ".repeat(expected_prefix_len - new_prefix.len());
-            format!("{}{}", new_prefix, padding)
-        } else {
-            new_prefix
+        let children = match buffered {
+            Some(children) => Box::new(children.into_iter()),
+            None => read_children_counted(entry, config, stats),
+        };
+        let mut has_children = false;
+        let mut children = children.peekable();
+        while let Some(child) = children.next() {
+            if ctx.truncated {
+                break;
+            }
+            let is_last_child = children.peek().is_none();
+            total += print_entry(&child, is_last_child, &child_prefix, ctx)?;
+            has_children = true;
+        }
+
+        // 空目录和未展开的目录不输出合计
+        if config.dir_totals && has_children {
+            let summary = format!("{}∑ {}", child_prefix, config.format_size(total));
+            writeln!(ctx.out, "{}", utils::dimmed(&summary, config.color))?;
+        }
+    }
+
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// 测试用目录结构：
+    ///
+    /// ```text
+    /// .hidden
+    /// a/b/build/
+    /// a/big          (3000字节)
+    /// build/
+    /// node_modules/x.js
+    /// small.txt      (5字节)
+    /// target         (普通文件)
+    /// ```
+    fn fixture() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("a/b/build")).unwrap();
+        fs::create_dir(root.join("build")).unwrap();
+        fs::create_dir(root.join("node_modules")).unwrap();
+        fs::write(root.join("node_modules/x.js"), "x").unwrap();
+        fs::write(root.join("a/big"), vec![0u8; 3000]).unwrap();
+        fs::write(root.join("small.txt"), "small").unwrap();
+        fs::write(root.join("target"), "t").unwrap();
+        fs::write(root.join(".hidden"), "h").unwrap();
+        dir
+    }
+
+    fn config(root: &Path) -> Config {
+        Config::new(root.to_path_buf()).with_format(OutputFormat::Tree)
+    }
+
+    #[test]
+    fn execute_roots_reads_each_path_from_the_reader() {
+        let dir = fixture();
+        let input = format!(
+            "{}\n\n{}\r\n{}\n",
+            dir.path().join("a/b").display(),
+            dir.path().join("does-not-exist").display(),
+            dir.path().join("small.txt").display(),
+        );
+        let mut out = Vec::new();
+        execute_roots(Cursor::new(input), &config(dir.path()), &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "b\n└── build\nsmall.txt\n");
+    }
+
+    #[test]
+    fn color_comes_from_config_not_global_state() {
+        let dir = fixture();
+        assert!(!render(&config(dir.path())).contains('\x1b'));
+        let colored = render(&config(dir.path()).with_color(true));
+        assert!(colored.contains("\x1b["));
+    }
+
+    fn render(config: &Config) -> String {
+        let mut out = Vec::new();
+        render_root(&config.root, config, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    /// 去掉第一行（临时目录名称不固定的根目录）
+    fn body(output: &str) -> Vec<&str> {
+        output.lines().skip(1).collect()
+    }
+
+    #[test]
+    fn child_count_reuses_children_read_for_rendering() {
+        let dir = fixture();
+        let counted = render(&config(dir.path()).with_child_count(true));
+        assert!(counted.contains("├── a (2)\n"));
+        assert!(counted.contains("│   │   └── build (0)\n"));
+
+        // 未展开的目录同样显示子条目数，展开的目录复用已读取的子条目
+        let shallow = render(
+            &config(dir.path())
+                .with_child_count(true)
+                .with_sort_by(SortBy::Size)
+                .with_max_depth(1)
+                .unwrap(),
+        );
+        assert!(shallow.contains("── a (2)\n"));
+        assert!(!shallow.contains("big"));
+        let expanded = render(&config(dir.path()).with_child_count(true).with_reverse(true));
+        assert!(expanded.contains("    ├── big\n"));
+        assert!(expanded.contains("    └── b (1)\n"));
+    }
+
+    #[test]
+    fn filter_stats_count_skips_by_reason_while_reading() {
+        let dir = fixture();
+        let config = config(dir.path()).with_extensions(["txt"]);
+        let root = TreeEntry::new(DirEntry::root(dir.path(), &config).unwrap(), 0);
+        let stats = FilterStats::default();
+        let kept: Vec<TreeEntry> = read_children_counted(&root, &config, Some(&stats)).collect();
+        assert_eq!(kept.len(), 3);
+        assert_eq!(
+            stats.summary(),
+            "examined 6 entries, 3 passed filters \
+             (default exclude: 1, extension mismatch: 1, hidden: 1)"
+        );
+    }
+
+    #[test]
+    fn buffering_walks_report_progress_per_entry() {
+        let dir = fixture();
+        let config = config(dir.path());
+        let root = TreeEntry::new(DirEntry::root(dir.path(), &config).unwrap(), 0);
+
+        let mut progress = Progress::disabled();
+        build_tree_with_progress(dir.path(), &config, &mut progress).unwrap();
+        assert_eq!(progress.scanned, 7);
+
+        let mut progress = Progress::disabled();
+        stats_by_extension_with_progress(dir.path(), &config, &mut progress).unwrap();
+        assert_eq!(progress.scanned, 7);
+
+        let mut progress = Progress::disabled();
+        summarize_dir(&root, &config, &mut SeenLinks::new(&config), &mut progress);
+        assert_eq!(progress.scanned, 7);
+
+        let mut progress = Progress::disabled();
+        max_size_width(&root, &config, &mut progress);
+        assert_eq!(progress.scanned, 7);
+    }
+
+    #[test]
+    fn diff_entries_reports_added_removed_and_changed() {
+        let dir = fixture();
+        let other = fixture();
+        fs::write(dir.path().join("small.txt"), "changed!").unwrap();
+        fs::write(dir.path().join("new"), "").unwrap();
+        fs::remove_file(other.path().join("target")).unwrap();
+        fs::write(other.path().join("gone"), "").unwrap();
+
+        let diff: Vec<(String, DiffStatus)> =
+            diff_entries(dir.path(), other.path(), &config(dir.path()))
+                .into_iter()
+                .map(|(path, status)| (path.display().to_string(), status))
+                .filter(|(_, status)| *status != DiffStatus::Unchanged)
+                .collect();
+        assert_eq!(
+            diff,
+            vec![
+                ("gone".to_string(), DiffStatus::Removed),
+                ("new".to_string(), DiffStatus::Added),
+                ("small.txt".to_string(), DiffStatus::Changed),
+                ("target".to_string(), DiffStatus::Added),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_git_status_keeps_entries_under_the_base() {
+        let status = " M src/main.rs\n?? src/new.rs\nR  old.rs -> src/renamed.rs\n!! target/\n";
+        let map = parse_git_status(status, Path::new("src"));
+        assert_eq!(map.get(Path::new("main.rs")).unwrap(), "M");
+        assert_eq!(map.get(Path::new("new.rs")).unwrap(), "??");
+        assert_eq!(map.get(Path::new("renamed.rs")).unwrap(), "R");
+        assert_eq!(map.len(), 3);
+    }
+
+    #[test]
+    fn git_status_marks_entries_in_a_repository() {
+        let dir = fixture();
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .arg("-C")
+                .arg(dir.path())
+                .args(args)
+                .output()
+                .is_ok_and(|output| output.status.success())
+        };
+        // 没有git时跳过，并在测试输出中说明原因
+        if !git(&["init", "-q"]) {
+            eprintln!("skipping git_status_marks_entries_in_a_repository: git is not available");
+            return;
+        }
+        fs::write(dir.path().join(".gitignore"), "target\n").unwrap();
+
+        let output = render(
+            &config(dir.path())
+                .with_show_git(true)
+                .with_max_depth(1)
+                .unwrap(),
+        );
+        assert!(output.contains("├── small.txt [??]\n"));
+        assert!(output.contains("└── target [!!]\n"));
+    }
+
+    #[test]
+    fn sorting_uses_metadata_cached_when_the_entry_was_read() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("x"), [0; 10]).unwrap();
+        fs::write(dir.path().join("y"), [0; 20]).unwrap();
+        let config = config(dir.path()).with_sort_by(SortBy::Size);
+        let root = TreeEntry::new(DirEntry::root(dir.path(), &config).unwrap(), 0);
+        let mut entries: Vec<TreeEntry> = read_children(&root, &config).collect();
+
+        // 读取后修改磁盘上的大小，排序仍按缓存值进行，说明没有再次stat
+        fs::write(dir.path().join("x"), [0; 30]).unwrap();
+        sort_entries(&mut entries, &config);
+        let sizes: Vec<(String, u64)> = entries
+            .iter()
+            .map(|e| (e.entry.file_name().to_string_lossy().into_owned(), e.size()))
+            .collect();
+        assert_eq!(sizes, [("x".to_string(), 10), ("y".to_string(), 20)]);
+    }
+
+    /// 第一次写入时执行回调的输出，用于观察输出与遍历的先后顺序
+    struct FirstWriteHook<F: FnMut()> {
+        out: Vec<u8>,
+        hook: Option<F>,
+    }
+
+    impl<F: FnMut()> Write for FirstWriteHook<F> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if let Some(mut hook) = self.hook.take() {
+                hook();
+            }
+            self.out.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn first_line_is_written_before_traversal_completes() {
+        let dir = fixture();
+        fs::create_dir(dir.path().join("zz")).unwrap();
+        // 第一行写出时才在尚未读取的目录中创建文件；
+        // 如果输出前已遍历完整棵树，新文件不会出现在输出中
+        let late = dir.path().join("zz/late.txt");
+        for sort_by in [SortBy::Name, SortBy::None] {
+            fs::remove_file(&late).ok();
+            let mut out = FirstWriteHook {
+                out: Vec::new(),
+                hook: Some(|| fs::write(&late, "").unwrap()),
+            };
+            render_root(
+                dir.path(),
+                &config(dir.path()).with_sort_by(sort_by),
+                &mut out,
+            )
+            .unwrap();
+            let output = String::from_utf8(out.out).unwrap();
+            assert!(output.contains("└── late.txt\n"), "{}", output);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn classify_marks_directories_executables_and_symlinks() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let dir = fixture();
+        let script = dir.path().join("run.sh");
+        fs::write(&script, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        symlink(dir.path().join("small.txt"), dir.path().join("link")).unwrap();
+
+        let output = render(
+            &config(dir.path())
+                .with_classify(true)
+                .with_max_depth(1)
+                .unwrap(),
+        );
+        assert_eq!(
+            output.lines().skip(1).collect::<Vec<_>>(),
+            [
+                "├── a/",
+                "├── build/",
+                "├── link@",
+                "├── run.sh*",
+                "├── small.txt",
+                "└── target",
+            ]
+        );
+    }
+
+    #[test]
+    fn node_metadata_captures_size_and_modification_time() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.bin");
+        fs::write(&path, [0; 1234]).unwrap();
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+
+        let entry = WalkDir::new(&path).into_iter().next().unwrap().unwrap();
+        let metadata = NodeMetadata::from_entry(&entry);
+        assert_eq!(metadata.size, Some(1234));
+        assert_eq!(metadata.modified, Some(modified));
+        assert!(!metadata.is_dir && !metadata.is_symlink);
+        assert!(metadata.permissions.is_some());
+    }
+
+    #[test]
+    fn full_path_shows_entries_relative_to_the_root() {
+        let dir = fixture();
+        let output = render(&config(dir.path()).with_path_display(PathDisplay::Relative));
+        assert!(output.contains("│   ├── a/b\n"));
+        assert!(output.contains("│   │   └── a/b/build\n"));
+        assert!(output.contains("│   └── a/big\n"));
+        assert!(output.contains("└── target\n"));
+    }
+
+    #[test]
+    fn count_only_prints_exact_totals_without_tree_lines() {
+        let dir = fixture();
+        let output = render(&config(dir.path()).with_count_only(true));
+        assert_eq!(output, "directories=4 files=3 bytes=3006\n");
+    }
+
+    #[test]
+    fn color_flag_overrides_terminal_detection() {
+        use crate::cli::ColorWhen;
+
+        let dir = fixture();
+        // 输出被重定向（不是终端）时--color always仍然输出转义序列
+        let always = config(dir.path()).with_color(ColorWhen::Always.enabled_for(false));
+        assert!(render(&always).contains("\x1b["));
+        let never = config(dir.path()).with_color(ColorWhen::Never.enabled_for(true));
+        assert!(!render(&never).contains('\x1b'));
+    }
+
+    #[test]
+    fn pager_writes_straight_through_when_not_a_terminal() {
+        use clap::Parser;
+
+        let dir = fixture();
+        let run = |flags: &[&str]| {
+            let args = TreeArgs::try_parse_from(
+                ["tree"]
+                    .iter()
+                    .chain(flags)
+                    .copied()
+                    .chain(dir.path().to_str()),
+            )
+            .unwrap();
+            let mut out = Vec::new();
+            execute_with(&args, &config(dir.path()), false, &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        let plain = run(&[]);
+        assert!(plain.ends_with("└── target\n"));
+        assert_eq!(run(&["--pager"]), plain);
+    }
+
+    #[test]
+    fn no_sort_keeps_the_order_the_filesystem_returns() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["m", "c", "x", "a", "q", "k", "e", "z"] {
+            fs::write(dir.path().join(name), "").unwrap();
+        }
+        let raw: Vec<String> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+
+        let names = |sort_by| {
+            render(&config(dir.path()).with_sort_by(sort_by))
+                .lines()
+                .skip(1)
+                .map(|line| line.rsplit(' ').next().unwrap().to_string())
+                .collect::<Vec<_>>()
         };
+        assert_eq!(names(SortBy::None), raw);
+        let mut sorted = raw;
+        sorted.sort();
+        assert_eq!(names(SortBy::Name), sorted);
+    }
 
-        let mut visited_paths = std::collections::HashSet::new();
-        let dir_entries = fs::read_dir(entry.path())?;
-        let mut children: Vec<DirEntry> = Vec::new();
-
-        for dir_result in dir_entries {
-            if let Ok(dir) = dir_result {
-                println!("Processing directory entry: {:?}", dir.path()); // 添加日志
-                let path = dir.path();
-                let canonical_path = fs::canonicalize(&path)?;
-                if !visited_paths.contains(&canonical_path) {
-                    visited_paths.insert(canonical_path);
-                    if let Ok(entry) = DirEntry::from_path(path.as_path()) {
-                        println!("Adding entry: {:?}", entry.path()); // 添加日志
-                        if filter_entry(&entry, config) {
-                            children.push(entry);
-                        }
-                    }
+    #[test]
+    fn tree_view_prunes_excluded_directories_but_keeps_files() {
+        let dir = fixture();
+        assert_eq!(
+            body(&render(&config(dir.path()))),
+            vec![
+                "├── a",
+                "│   ├── b",
+                "│   │   └── build",
+                "│   └── big",
+                "├── build",
+                "├── small.txt",
+                "└── target",
+            ]
+        );
+    }
+
+    /// 检查XML是否格式良好（标签正确嵌套、唯一根元素、属性值已转义），返回元素数量
+    ///
+    /// 只覆盖`write_xml`产生的子集：每行一个开始、结束或自闭合标签。
+    fn check_xml(xml: &str) -> usize {
+        let mut lines = xml.lines();
+        assert_eq!(
+            lines.next(),
+            Some(r#"<?xml version="1.0" encoding="UTF-8"?>"#)
+        );
+        let mut open: Vec<&str> = Vec::new();
+        let (mut elements, mut roots) = (0, 0);
+        for line in lines {
+            let tag = line.trim_start();
+            assert!(tag.starts_with('<') && tag.ends_with('>'), "{}", line);
+            let inner = &tag[1..tag.len() - 1];
+            if let Some(name) = inner.strip_prefix('/') {
+                assert_eq!(open.pop(), Some(name), "{}", line);
+                continue;
+            }
+            let (inner, closed) = match inner.strip_suffix('/') {
+                Some(inner) => (inner, true),
+                None => (inner, false),
+            };
+            let name = inner.split(' ').next().unwrap();
+            // 属性值中不能出现未转义的<、>、"或&
+            for value in inner.split('"').skip(1).step_by(2) {
+                assert!(!value.contains(['<', '>']), "{}", line);
+                for (i, _) in value.match_indices('&') {
+                    let entity = &value[i..];
+                    assert!(
+                        ["&amp;", "&lt;", "&gt;", "&quot;", "&apos;"]
+                            .iter()
+                            .any(|e| entity.starts_with(e)),
+                        "{}",
+                        line
+                    );
                 }
             }
+            if open.is_empty() {
+                roots += 1;
+            }
+            elements += 1;
+            if !closed {
+                open.push(name);
+            }
         }
+        assert!(open.is_empty(), "unclosed: {:?}", open);
+        assert_eq!(roots, 1);
+        elements
+    }
 
-        sort_entries(&mut children, config);
+    #[test]
+    fn xml_output_is_well_formed_with_one_element_per_node() {
+        let dir = fixture();
+        fs::write(dir.path().join("a&b'c.txt"), "x").unwrap();
+        let config = config(dir.path())
+            .with_format(OutputFormat::Xml)
+            .with_show_permissions(true);
+        let xml = render(&config);
+        assert!(xml.contains(r#"<file name="a&amp;b&apos;c.txt" size="1""#));
 
-        for (i, child) in children.iter().enumerate() {
-            let is_last_child = i == children.len() - 1;
-            print_entry(&child, root, is_last_child, &new_prefix, config)?;
+        fn count(node: &TreeNode) -> usize {
+            1 + node.children.iter().map(count).sum::<usize>()
         }
+        let tree = build_tree(dir.path(), &config).unwrap();
+        assert_eq!(check_xml(&xml), count(&tree));
+        assert_eq!(count(&tree), 9);
     }
 
-    Ok(())
+    /// 返回包含`name`的输出行
+    fn line_of<'a>(output: &'a str, name: &str) -> &'a str {
+        output
+            .lines()
+            .find(|line| line.contains(&format!(" {}\x1b", name)))
+            .unwrap_or_else(|| panic!("no line for {} in {:?}", name, output))
+    }
+
+    #[test]
+    fn depth_colors_differ_between_adjacent_levels() {
+        let dir = fixture();
+        let output = render(&config(dir.path()).with_depth_colors(true).with_color(true));
+        let depth1 = line_of(&output, "a");
+        let depth2 = line_of(&output, "b");
+        assert!(depth1.starts_with(&format!("\x1b[1;{}m", Color::Green.to_fg_str())));
+        assert!(depth2.starts_with(&format!("\x1b[1;{}m", Color::Yellow.to_fg_str())));
+    }
+
+    #[test]
+    fn age_colors_follow_the_modification_time() {
+        let dir = tempfile::tempdir().unwrap();
+        let day = Duration::from_secs(86_400);
+        for (name, age) in [
+            ("fresh", Duration::ZERO),
+            ("week", 7 * day),
+            ("old", 90 * day),
+        ] {
+            let path = dir.path().join(name);
+            fs::write(&path, "").unwrap();
+            fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(SystemTime::now() - age)
+                .unwrap();
+        }
+
+        let output = render(&config(dir.path()).with_age_colors(true).with_color(true));
+        for (name, color) in [
+            ("fresh", Color::Green),
+            ("week", Color::Yellow),
+            ("old", Color::Red),
+        ] {
+            let line = line_of(&output, name);
+            assert!(
+                line.starts_with(&format!("\x1b[{}m", color.to_fg_str())),
+                "{:?}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn traversal_errors_keep_the_offending_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing");
+        let err = Error::from(
+            WalkDir::new(&missing)
+                .into_iter()
+                .next()
+                .unwrap()
+                .unwrap_err(),
+        );
+        assert!(
+            matches!(&err, Error::Traversal { path: Some(path), depth: 0, .. } if *path == missing)
+        );
+        assert!(err
+            .to_string()
+            .starts_with(&format!("Failed to traverse {}: ", missing.display())));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let locked = dir.path().join("locked");
+            fs::create_dir(&locked).unwrap();
+            fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+            let denied = WalkDir::new(dir.path()).into_iter().find_map(|e| e.err());
+            fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+            match denied {
+                // root不受权限位限制，无法构造权限错误
+                None => eprintln!("skipping permission check: running with elevated privileges"),
+                Some(err) => {
+                    let err = Error::from(err);
+                    assert!(matches!(
+                        &err,
+                        Error::Traversal { path: Some(path), depth: 1, .. } if *path == locked
+                    ));
+                    assert!(err.to_string().contains(&locked.display().to_string()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn missing_root_is_a_path_access_error() {
+        use clap::Parser;
+
+        let root = Path::new("/nonexistent/toolboxer-test-root");
+        let args = TreeArgs::try_parse_from(["tree", root.to_str().unwrap()]).unwrap();
+        let mut out = Vec::new();
+        let err = execute_with(&args, &config(root), false, &mut out).unwrap_err();
+        assert!(matches!(&err, Error::PathAccess(path) if path == root));
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn dir_totals_print_the_cumulative_size_below_each_directory() {
+        let dir = fixture();
+        let totals = render(&config(dir.path()).with_dir_totals(true));
+        // a下只有big（3000字节）计入合计，空目录不输出合计行
+        assert!(
+            totals.contains("│   └── big\n│   ∑ 2.93 KiB\n"),
+            "{}",
+            totals
+        );
+        assert!(totals.ends_with("└── target\n∑ 2.94 KiB\n"), "{}", totals);
+        assert!(!totals.contains("build\n│   │   │   ∑"));
+    }
+
+    #[test]
+    fn equal_sizes_are_listed_in_name_order() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["delta", "alpha", "charlie", "bravo"] {
+            fs::write(dir.path().join(name), [0; 64]).unwrap();
+        }
+        fs::write(dir.path().join("big"), [0; 128]).unwrap();
+
+        let output = render(&config(dir.path()).with_sort_by(SortBy::Size));
+        assert_eq!(
+            body(&output),
+            [
+                "├── alpha",
+                "├── bravo",
+                "├── charlie",
+                "├── delta",
+                "└── big"
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_root_is_followed_unless_no_deref_args() {
+        let dir = fixture();
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink(dir.path().join("a"), &link).unwrap();
+
+        assert_eq!(
+            render(&config(&link)),
+            "link\n├── b\n│   └── build\n└── big\n"
+        );
+        assert_eq!(render(&config(&link).with_no_deref_args(true)), "link\n");
+    }
+
+    #[test]
+    fn porcelain_writes_tab_separated_fields() {
+        let dir = fixture();
+        let output = render(&config(dir.path()).with_format(OutputFormat::Porcelain));
+        let modified = fs::metadata(dir.path().join("a/big"))
+            .unwrap()
+            .modified()
+            .unwrap()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        let fields = |path: &str| {
+            output
+                .lines()
+                .map(|line| line.split('\t').collect::<Vec<_>>())
+                .find(|fields| fields.last() == Some(&path))
+                .unwrap()
+        };
+        assert_eq!(fields(".")[..3], ["d", "0", "-"]);
+        assert_eq!(
+            fields("a/big"),
+            ["f", "2", "3000", &modified.to_string(), "a/big"]
+        );
+        assert_eq!(fields("a/b")[..3], ["d", "2", "-"]);
+        assert_eq!(output.lines().count(), 8);
+    }
+
+    #[test]
+    fn date_sort_keeps_directories_grouped() {
+        let dir = tempfile::tempdir().unwrap();
+        let day = Duration::from_secs(86_400);
+        for (name, age) in [("mid", 5 * day), ("new", day), ("old", 10 * day)] {
+            let path = dir.path().join(name);
+            fs::write(&path, "").unwrap();
+            fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(SystemTime::now() - age)
+                .unwrap();
+        }
+        // 目录的修改时间为当前时间，晚于所有文件；按名称顺序创建，
+        // 时间戳精度不足导致两者相同时也按名称排序，结果一致
+        fs::create_dir(dir.path().join("adir")).unwrap();
+        fs::create_dir(dir.path().join("zdir")).unwrap();
+
+        let names = |config: Config| {
+            render(&config.with_sort_by(SortBy::Date))
+                .lines()
+                .skip(1)
+                .map(|line| line.rsplit(' ').next().unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(config(dir.path()).with_dirs_first(true)),
+            ["adir", "zdir", "old", "mid", "new"]
+        );
+        assert_eq!(
+            names(config(dir.path()).with_dirs_first(true).with_reverse(true)),
+            ["zdir", "adir", "new", "mid", "old"]
+        );
+    }
+
+    #[test]
+    fn paths_format_honors_null_separator() {
+        let dir = fixture();
+        let output = render(
+            &config(&dir.path().join("a/b"))
+                .with_format(OutputFormat::Paths)
+                .with_null_separated(true),
+        );
+        let b = dir.path().join("a/b");
+        assert_eq!(
+            output,
+            format!("{}\0{}\0", b.display(), b.join("build").display())
+        );
+    }
+
+    #[test]
+    fn max_name_width_truncates_long_names() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a_very_long_name.txt"), "").unwrap();
+        fs::write(dir.path().join("short.txt"), "").unwrap();
+        let output = render(&config(dir.path()).with_max_name_width(Some(12)));
+        assert_eq!(body(&output), ["├── a_very…e.txt", "└── short.txt"]);
+    }
+
+    #[test]
+    fn exclude_patterns_with_slash_only_match_directories() {
+        let dir = fixture();
+        let mut config = config(dir.path());
+        config.exclude_patterns = vec!["build/".to_string(), "*.txt".to_string()];
+        assert_eq!(
+            body(&render(&config)),
+            vec!["├── a", "│   ├── b", "│   └── big", "└── target"]
+        );
+    }
+
+    #[test]
+    fn summary_tree_aggregates_subtrees() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("outer/inner")).unwrap();
+        fs::create_dir(dir.path().join("other")).unwrap();
+        fs::write(dir.path().join("top.txt"), vec![0; 10]).unwrap();
+        fs::write(dir.path().join("other/x"), "y").unwrap();
+        fs::write(dir.path().join("outer/o.txt"), vec![0; 1024]).unwrap();
+        fs::write(dir.path().join("outer/inner/a"), vec![0; 2048]).unwrap();
+        fs::write(dir.path().join("outer/inner/b"), vec![0; 1024]).unwrap();
+
+        let output = render(&config(dir.path()).with_format(OutputFormat::SummaryTree));
+        assert_eq!(
+            body(&output),
+            [
+                "├── other (1 file, 1 B)",
+                "└── outer (3 files, 4 KiB)",
+                "    └── inner (2 files, 3 KiB)",
+            ]
+        );
+        assert!(output
+            .lines()
+            .next()
+            .unwrap()
+            .ends_with(" (5 files, 4.01 KiB)"));
+    }
+
+    #[test]
+    fn jsonl_lines_parse_alone_and_link_parents() {
+        let dir = fixture();
+        let jsonl = render(&config(dir.path()).with_format(OutputFormat::Jsonl));
+        let objects: Vec<serde_json::Value> = jsonl
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(objects[0]["parent"], serde_json::Value::Null);
+        let big = objects.iter().find(|o| o["path"] == "a/big").unwrap();
+        assert_eq!(big["parent"], "a");
+        assert_eq!(big["depth"], 2);
+    }
+
+    #[test]
+    fn stats_by_extension_groups_files() {
+        let dir = fixture();
+        fs::write(dir.path().join("notes.txt"), "abc").unwrap();
+        let stats = stats_by_extension(dir.path(), &config(dir.path())).unwrap();
+        assert_eq!(
+            stats[0],
+            ExtensionStats {
+                extension: "(none)".to_string(),
+                files: 2,
+                bytes: 3001,
+            }
+        );
+        assert_eq!(stats[1].extension, "txt");
+        assert_eq!(stats[1].files, 2);
+        assert_eq!(stats[1].bytes, 8);
+    }
+
+    #[test]
+    fn auto_color_emits_no_escapes_when_not_a_terminal() {
+        use crate::cli::ColorWhen;
+
+        let dir = fixture();
+        assert!(!ColorWhen::Auto.enabled_for(false));
+        let piped = config(dir.path()).with_color(ColorWhen::Auto.enabled_for(false));
+        assert!(!render(&piped).contains('\x1b'));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn count_links_once_counts_hardlinks_once() {
+        let dir = fixture();
+        fs::hard_link(dir.path().join("a/big"), dir.path().join("big-link")).unwrap();
+        let twice = summarize(dir.path(), &config(dir.path())).unwrap();
+        let once = summarize(dir.path(), &config(dir.path()).with_count_links_once(true)).unwrap();
+        assert_eq!(twice.bytes, 6006);
+        assert_eq!(once.bytes, 3006);
+        assert_eq!(once.files, twice.files);
+    }
+
+    #[test]
+    fn only_depth_lists_a_single_level() {
+        let dir = fixture();
+        let output = render(&config(dir.path()).with_only_depth(Some(2)));
+        assert_eq!(output, "a/b\na/big\n");
+        assert_eq!(render(&config(dir.path()).with_only_depth(Some(0))), ".\n");
+    }
+
+    #[test]
+    fn sorting_supports_reverse_and_dirs_first() {
+        let dir = fixture();
+        let reversed = render(
+            &config(dir.path())
+                .with_reverse(true)
+                .with_max_depth(1)
+                .unwrap(),
+        );
+        assert_eq!(
+            body(&reversed),
+            vec!["├── target", "├── small.txt", "├── build", "└── a"]
+        );
+
+        let by_size = render(
+            &config(dir.path())
+                .with_sort_by(SortBy::Size)
+                .with_reverse(true)
+                .with_dirs_first(true)
+                .with_max_depth(1)
+                .unwrap(),
+        );
+        let body = body(&by_size);
+        assert_eq!(&body[2..], ["├── small.txt", "└── target"]);
+    }
+
+    #[test]
+    fn json_nests_children_and_pretty_matches_compact() {
+        let dir = fixture();
+        let json_config = config(dir.path())
+            .with_format(OutputFormat::Json)
+            .with_show_size(true);
+        let compact = render(&json_config);
+        let json: serde_json::Value = serde_json::from_str(&compact).unwrap();
+        assert_eq!(compact.lines().count(), 1);
+        assert_eq!(json["path"], ".");
+        assert_eq!(json["children"][0]["name"], "a");
+        assert_eq!(json["children"][0]["children"][1]["size"], 3000);
+        assert!(json["children"][2].get("children").is_none());
+
+        let pretty = render(&json_config.with_pretty_json(true));
+        assert!(pretty.lines().count() > 1);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&pretty).unwrap(),
+            json
+        );
+    }
+
+    #[test]
+    fn find_dirs_lists_matches_at_any_depth() {
+        let dir = fixture();
+        let output = render(&config(dir.path()).with_find_dirs(Some("build".into())));
+        let expected: Vec<String> = ["a/b/build", "build"]
+            .iter()
+            .map(|path| dir.path().join(path).display().to_string())
+            .collect();
+        assert_eq!(output.lines().collect::<Vec<_>>(), expected);
+
+        let shallow = config(dir.path())
+            .with_find_dirs(Some("build".into()))
+            .with_max_depth(1)
+            .unwrap();
+        assert_eq!(render(&shallow).lines().count(), 1);
+    }
+
+    #[test]
+    fn print_root_shows_the_canonical_root() {
+        let dir = fixture();
+        let nested = dir.path().join("a/../a/b");
+        let output = render(&config(&nested).with_print_root(true));
+        assert_eq!(
+            output.lines().next().unwrap(),
+            fs::canonicalize(dir.path().join("a/b"))
+                .unwrap()
+                .display()
+                .to_string()
+        );
+        assert_eq!(output.lines().nth(1), Some("b"));
+
+        let paths = render(
+            &config(&nested)
+                .with_print_root(true)
+                .with_format(OutputFormat::Paths),
+        );
+        assert_eq!(paths.lines().next(), Some(nested.to_str().unwrap()));
+    }
+
+    #[test]
+    fn align_sizes_right_aligns_a_column() {
+        let dir = fixture();
+        let output = render(
+            &config(dir.path())
+                .with_show_size(true)
+                .with_align_sizes(true)
+                .with_max_depth(1)
+                .unwrap(),
+        );
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[1], "    ├── a");
+        assert_eq!(lines[3], "5 B ├── small.txt");
+        assert_eq!(lines[4], "1 B └── target");
+
+        let nested = render(
+            &config(dir.path())
+                .with_show_size(true)
+                .with_align_sizes(true),
+        );
+        assert!(nested.contains("2.93 KiB │   └── big\n"));
+    }
+
+    #[test]
+    fn manifest_skips_files_above_the_threshold() {
+        let dir = fixture();
+        let output = render(
+            &config(dir.path())
+                .with_format(OutputFormat::Manifest)
+                .with_skip_larger_than(Some(1024)),
+        );
+        assert!(output.contains("(skipped, too large)  a/big\n"));
+        assert!(output.contains(&format!(
+            "{}  small.txt\n",
+            utils::sha256_file(&dir.path().join("small.txt")).unwrap()
+        )));
+
+        let summary = summarize(
+            dir.path(),
+            &config(dir.path()).with_skip_larger_than(Some(1024)),
+        )
+        .unwrap();
+        assert_eq!(summary.bytes, 6);
+    }
+
+    #[test]
+    fn skeleton_lists_parents_before_children() {
+        let dir = fixture();
+        let output = render(&config(dir.path()).with_format(OutputFormat::Skeleton));
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "a/",
+                "a/b/",
+                "a/b/build/",
+                "a/big",
+                "build/",
+                "small.txt",
+                "target"
+            ]
+        );
+        for (i, line) in lines.iter().enumerate() {
+            if let Some((parent, _)) = line.trim_end_matches('/').rsplit_once('/') {
+                let parent = format!("{}/", parent);
+                assert!(
+                    lines[..i].contains(&parent.as_str()),
+                    "{} before {}",
+                    parent,
+                    line
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn skip_reason_explains_filtered_entries() {
+        let dir = fixture();
+        let config = config(dir.path()).with_extensions(["txt"]);
+        let reason = |name: &str| {
+            let entry = WalkDir::new(dir.path().join(name))
+                .into_iter()
+                .next()
+                .unwrap()
+                .unwrap();
+            skip_reason(&entry, &NodeMetadata::from_entry(&entry), &config)
+        };
+        assert_eq!(reason("node_modules"), Some("default exclude"));
+        assert_eq!(reason(".hidden"), Some("hidden"));
+        assert_eq!(reason("target"), Some("extension mismatch"));
+        assert_eq!(reason("small.txt"), None);
+        assert_eq!(reason("a"), None);
+
+        let patterns = Config::new(dir.path().to_path_buf()).with_pattern(Some("sm".into()));
+        let patterns = patterns.unwrap();
+        let entry = WalkDir::new(dir.path().join("target"))
+            .into_iter()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            skip_reason(&entry, &NodeMetadata::from_entry(&entry), &patterns),
+            Some("pattern mismatch")
+        );
+    }
+
+    #[test]
+    fn max_files_truncates_output() {
+        let dir = fixture();
+        let output = render(&config(dir.path()).with_max_files(Some(3)));
+        assert_eq!(output.lines().count(), 3);
+    }
 }
@@ -3,8 +3,235 @@
 //! This module provides configuration structures and methods for managing
 //! various settings and options used throughout the application.
 
-use crate::error::Result;
-use std::path::PathBuf;
+use crate::cli::{PortownArgs, TreeArgs};
+use crate::error::{Error, Result};
+use crate::logging;
+use serde::{Deserialize, Deserializer};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Environment variable that overrides the config file location
+pub const CONFIG_ENV: &str = "TOOLBOXER_CONFIG";
+
+/// Environment variable with the default tree depth
+pub const MAX_DEPTH_ENV: &str = "TOOLBOXER_MAX_DEPTH";
+
+/// Environment variable with the default tree sort order
+pub const SORT_ENV: &str = "TOOLBOXER_SORT";
+
+/// Environment variable that disables color unless `--color` is given
+pub const NO_COLOR_ENV: &str = "TOOLBOXER_NO_COLOR";
+
+/// Returns whether `TOOLBOXER_NO_COLOR` is set to a non-empty value
+pub fn no_color_from_env() -> bool {
+    std::env::var_os(NO_COLOR_ENV).is_some_and(|value| !value.is_empty())
+}
+
+/// Settings loaded from the TOML config file
+///
+/// Every section is optional; missing keys fall back to the defaults.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FileConfig {
+    /// Defaults for the tree command
+    pub tree: TreeConfig,
+    /// Defaults for the portown command
+    pub portown: PortownConfig,
+    /// Output colors for tree and portown
+    pub theme: Theme,
+}
+
+/// Defaults for the tree command (`[tree]` section)
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TreeConfig {
+    /// Root path used when no path is given on the command line
+    pub default_path: Option<PathBuf>,
+}
+
+impl TreeConfig {
+    /// Applies the configured defaults to command line arguments
+    ///
+    /// `default_path` only applies when the path argument was omitted, so an
+    /// explicit `.` still shows the current directory.
+    ///
+    /// # Arguments
+    /// * `args` - Parsed tree arguments to update in place
+    pub fn apply(&self, args: &mut TreeArgs) {
+        if args.path.is_none() {
+            args.path = self.default_path.clone();
+        }
+    }
+}
+
+/// Default filters for the portown command (`[portown]` section)
+///
+/// These apply unless the corresponding flags are given on the command line.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PortownConfig {
+    /// Only show TCP connections
+    pub tcp_only: bool,
+    /// Only show UDP connections
+    pub udp_only: bool,
+    /// Only show listening ports
+    pub listen: bool,
+    /// Only show established connections
+    pub established_only: bool,
+    /// Annotate well-known service names
+    pub services: bool,
+}
+
+/// A color used by the theme, or `None` to leave the text uncolored
+///
+/// Deserialized from a color name such as `"blue"` or `"bright red"`, or
+/// `"none"`. Unknown names are rejected when the config file is loaded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThemeColor(pub Option<colored::Color>);
+
+impl ThemeColor {
+    /// Parses a color name, returning `None` for unknown names
+    ///
+    /// # Arguments
+    /// * `name` - Color name (case-insensitive), or `none`
+    pub fn parse(name: &str) -> Option<Self> {
+        if name.eq_ignore_ascii_case("none") {
+            return Some(Self(None));
+        }
+        name.parse().ok().map(|color| Self(Some(color)))
+    }
+}
+
+impl<'de> Deserialize<'de> for ThemeColor {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Self::parse(&name)
+            .ok_or_else(|| serde::de::Error::custom(format!("unknown color `{}`", name)))
+    }
+}
+
+/// Colors for each output role (`[theme]` section)
+///
+/// Roles left out of the config file keep the built-in colors, which are
+/// the ones used before themes existed.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    /// Directory names in the tree
+    pub directory: ThemeColor,
+    /// Executable file names in the tree
+    pub executable: ThemeColor,
+    /// Source file names (`.rs`, `.toml`) in the tree
+    pub source: ThemeColor,
+    /// File sizes shown by `--size`
+    pub size: ThemeColor,
+    /// Modification times shown by `--mod`
+    pub date: ThemeColor,
+    /// The TCP protocol column in portown
+    pub tcp: ThemeColor,
+    /// The UDP protocol column in portown
+    pub udp: ThemeColor,
+    /// The LISTENING state in portown
+    pub listening: ThemeColor,
+    /// The ESTABLISHED state in portown
+    pub established: ThemeColor,
+    /// Background of every other row in the portown table
+    pub stripe: ThemeColor,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        use colored::Color;
+        Self {
+            directory: ThemeColor(Some(Color::Blue)),
+            executable: ThemeColor(Some(Color::Green)),
+            source: ThemeColor(None),
+            size: ThemeColor(Some(Color::Green)),
+            date: ThemeColor(Some(Color::Yellow)),
+            tcp: ThemeColor(Some(Color::Green)),
+            udp: ThemeColor(Some(Color::Yellow)),
+            listening: ThemeColor(Some(Color::Yellow)),
+            established: ThemeColor(Some(Color::Green)),
+            // Dark gray: exactly ANSI 256 grayscale 236, so `termcolor_color`
+            // emits `Ansi256(236)` rather than a true-color escape
+            stripe: ThemeColor(Some(crate::utils::ansi256_gray(236))),
+        }
+    }
+}
+
+impl FileConfig {
+    /// Returns the config file location
+    ///
+    /// Uses `TOOLBOXER_CONFIG` if set, otherwise `.toolboxer.toml` in the
+    /// user's home directory.
+    pub fn path() -> Option<PathBuf> {
+        if let Some(path) = std::env::var_os(CONFIG_ENV) {
+            return Some(PathBuf::from(path));
+        }
+
+        std::env::var_os("HOME")
+            .or_else(|| std::env::var_os("USERPROFILE"))
+            .map(|home| PathBuf::from(home).join(".toolboxer.toml"))
+    }
+
+    /// Loads the config file, returning defaults when it does not exist
+    ///
+    /// # Returns
+    /// * `Ok(FileConfig)` - Parsed or default configuration
+    /// * `Err(Error)` - If the file cannot be read or is not valid TOML
+    pub fn load() -> Result<Self> {
+        match Self::path() {
+            Some(path) if path.exists() => Self::from_path(&path),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// Loads and parses the config file at `path`
+    ///
+    /// # 参数
+    /// * `path` - 配置文件路径
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        toml::from_str(&content).map_err(|e| Error::Config(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Parses config file content
+    ///
+    /// # 参数
+    /// * `content` - TOML格式的配置内容
+    pub fn parse(content: &str) -> Result<Self> {
+        toml::from_str(content).map_err(|e| Error::Config(e.to_string()))
+    }
+}
+
+impl PortownConfig {
+    /// Applies the configured defaults to command line arguments
+    ///
+    /// Defaults only fill in options the user left unset: a protocol default
+    /// is skipped when `--tcp`/`--udp` is given, and a state default is
+    /// skipped when `--listen`/`--established-only` is given.
+    ///
+    /// # Arguments
+    /// * `args` - Parsed portown arguments to update in place
+    pub fn apply(&self, args: &mut PortownArgs) {
+        if !args.tcp && !args.udp {
+            args.tcp = self.tcp_only;
+            args.udp = self.udp_only;
+        }
+
+        if !args.listen && !args.established_only {
+            args.listen = self.listen;
+            args.established_only = self.established_only;
+        }
+
+        args.services |= self.services;
+    }
+}
+
+/// Directory names excluded from the tree unless `--no-default-excludes` is given
+pub const DEFAULT_EXCLUDES: &[&str] = &[".git", "node_modules", "target", "__pycache__"];
 
 /// Configuration structure for command execution
 ///
@@ -18,24 +245,95 @@ pub struct Config {
     pub root: PathBuf,
     /// Maximum depth to traverse (None for unlimited)
     pub max_depth: Option<usize>,
+    /// Print only the entries at exactly this depth, as a flat list of relative paths
+    pub only_depth: Option<usize>,
+    /// Print the full paths of directories whose names match this glob, as a flat list
+    pub find_dirs: Option<String>,
     /// Whether to show hidden files
     pub show_hidden: bool,
     /// How to sort the directory entries
     pub sort_by: SortBy,
+    /// Whether the sort order is reversed
+    pub reverse: bool,
     /// Whether to show file permissions
     pub show_permissions: bool,
     /// Whether to show file sizes
     pub show_size: bool,
+    /// Whether sizes use decimal SI units (kB, MB) instead of binary (KiB, MiB)
+    pub si_units: bool,
+    /// Whether sizes are shown as a right-aligned column before the tree instead of after names
+    pub align_sizes: bool,
     /// Whether to show modification dates
     pub show_date: bool,
+    /// Whether recent modification dates are shown relative to now (`3 hours ago`)
+    pub relative_dates: bool,
+    /// Whether `Json` output is indented instead of a single compact line
+    pub pretty_json: bool,
     /// Optional pattern for filtering files
     pub pattern: Option<String>,
+    /// Whether to annotate entries with their git status
+    pub show_git: bool,
+    /// Maximum number of entries to print (None for unlimited)
+    pub max_files: Option<usize>,
+    /// Whether to append type indicators (`/`, `*`, `@`) to names
+    pub classify: bool,
+    /// How entry labels are rendered
+    pub path_display: PathDisplay,
+    /// Whether to print only the entry counts instead of the tree
+    pub count_only: bool,
+    /// Whether to print per-extension file counts and sizes instead of the tree
+    pub stats_by_ext: bool,
+    /// Whether hard links to the same file add its size only once (Unix only)
+    pub count_links_once: bool,
+    /// Minimum file size in bytes (inclusive)
+    pub min_size: Option<u64>,
+    /// Maximum file size in bytes (inclusive)
+    pub max_size: Option<u64>,
+    /// Files above this size are neither hashed nor counted in size totals
+    pub skip_larger_than: Option<u64>,
+    /// Only show files modified at or after this time
+    pub newer_than: Option<SystemTime>,
+    /// Only show files modified at or before this time
+    pub older_than: Option<SystemTime>,
+    /// Lowercase file extensions (without dot) to show; empty shows all files
+    pub extensions: HashSet<String>,
+    /// Whether directories are listed before files, keeping the sort order in each group
+    pub dirs_first: bool,
+    /// Whether the tree view starts with the canonicalized absolute root path as a header
+    pub print_root: bool,
+    /// Directory names that are pruned without descending; files with these names are kept
+    pub excludes: HashSet<String>,
+    /// Glob patterns (`*`, `?`) matched against entry names and pruned like `excludes`;
+    /// a trailing `/` restricts the pattern to directories
+    pub exclude_patterns: Vec<String>,
+    /// Output format
+    pub format: OutputFormat,
+    /// Whether to tint entries by nesting level
+    pub depth_colors: bool,
+    /// Whether to color file names by modification age
+    pub age_colors: bool,
+    /// Whether to print each directory's cumulative size after its children
+    pub dir_totals: bool,
+    /// Whether to append the number of direct (filtered) children to directories
+    pub child_count: bool,
+    /// Maximum displayed name width; longer names are shortened in the middle
+    pub max_name_width: Option<usize>,
+    /// Base directory that displayed paths are made relative to
+    pub relative_to: Option<PathBuf>,
+    /// Whether a symlinked root is shown as a link instead of being followed
+    pub no_deref_args: bool,
+    /// Output colors
+    pub theme: Theme,
+    /// Whether `Paths` output terminates each path with NUL instead of a newline
+    pub null_separated: bool,
+    /// Whether output is written with ANSI colors (from `--color`)
+    pub color: bool,
 }
 
 /// Enumeration of available sorting methods
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
 pub enum SortBy {
-    /// Sort by file/directory name
+    /// Sort alphabetically by file/directory name
     Name,
     /// Sort by entry type (directories first)
     Type,
@@ -43,6 +341,58 @@ pub enum SortBy {
     Size,
     /// Sort by modification date
     Date,
+    /// Keep the order the filesystem returns entries in (fastest)
+    None,
+}
+
+/// Output format of the tree command
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// `Tree` when stdout is a terminal, `Paths` when it is piped or redirected (default)
+    Auto,
+    /// Indented text tree
+    Tree,
+    /// Nested `<directory>`/`<file>` XML elements
+    Xml,
+    /// Stable tab-separated `type depth size mtime path` lines for scripts
+    Porcelain,
+    /// One path per line, like `find`
+    Paths,
+    /// A single nested JSON document (the whole tree is kept in memory)
+    Json,
+    /// One standalone JSON object per entry, streamed during traversal
+    Jsonl,
+    /// `sha256  path` lines for every regular file, like `sha256sum`
+    Manifest,
+    /// One line per directory with its aggregated file count and size, like `du`
+    SummaryTree,
+    /// Relative paths with a trailing `/` on directories, parents before children
+    Skeleton,
+}
+
+/// How each tree entry's label is rendered
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathDisplay {
+    /// Only the file/directory name
+    Name,
+    /// Path relative to the tree root
+    Relative,
+    /// Absolute path
+    Absolute,
+}
+
+/// Settings that a higher-precedence layer (the command line) can set explicitly
+///
+/// `None` means the setting was not given, so the value from the lower layer
+/// (environment variables) is kept; see [`Config::merge`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ConfigOverrides {
+    /// Maximum depth to traverse
+    pub max_depth: Option<usize>,
+    /// Sort order for directory entries
+    pub sort_by: Option<SortBy>,
+    /// Whether the sort order is reversed
+    pub reverse: Option<bool>,
 }
 
 impl Config {
@@ -58,12 +408,117 @@ impl Config {
             directories_only: false,
             root,
             max_depth: None,
+            only_depth: None,
+            find_dirs: None,
             show_hidden: false,
             sort_by: SortBy::Name,
+            reverse: false,
             show_permissions: false,
             show_size: false,
+            si_units: false,
+            align_sizes: false,
             show_date: false,
+            relative_dates: false,
+            pretty_json: false,
             pattern: None,
+            show_git: false,
+            max_files: None,
+            classify: false,
+            path_display: PathDisplay::Name,
+            count_only: false,
+            stats_by_ext: false,
+            count_links_once: false,
+            min_size: None,
+            max_size: None,
+            skip_larger_than: None,
+            newer_than: None,
+            older_than: None,
+            extensions: HashSet::new(),
+            dirs_first: false,
+            print_root: false,
+            excludes: DEFAULT_EXCLUDES
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            exclude_patterns: Vec::new(),
+            format: OutputFormat::Auto,
+            depth_colors: false,
+            age_colors: false,
+            dir_totals: false,
+            child_count: false,
+            max_name_width: None,
+            relative_to: None,
+            no_deref_args: false,
+            theme: Theme::default(),
+            null_separated: false,
+            color: false,
+        }
+    }
+
+    /// Creates a configuration seeded from environment variables
+    ///
+    /// Reads `TOOLBOXER_MAX_DEPTH` (a number) and `TOOLBOXER_SORT`
+    /// (`name`, `type`, `size`, `date` or `none`). Invalid values are
+    /// reported as warnings and ignored. Apply command line settings on top
+    /// with [`Config::merge`] so flags take precedence.
+    ///
+    /// # Arguments
+    /// * `root` - Root directory path
+    pub fn from_env(root: PathBuf) -> Self {
+        let mut config = Config::new(root);
+
+        if let Ok(value) = std::env::var(MAX_DEPTH_ENV) {
+            match value.trim().parse() {
+                Ok(depth) => config.max_depth = Some(depth),
+                Err(_) => logging::warn(format!(
+                    "ignoring {}={}: not a valid depth",
+                    MAX_DEPTH_ENV, value
+                )),
+            }
+        }
+
+        if let Ok(value) = std::env::var(SORT_ENV) {
+            match <SortBy as clap::ValueEnum>::from_str(value.trim(), true) {
+                Ok(sort_by) => config.sort_by = sort_by,
+                Err(_) => logging::warn(format!(
+                    "ignoring {}={}: expected name, type, size, date or none",
+                    SORT_ENV, value
+                )),
+            }
+        }
+
+        config
+    }
+
+    /// Layers explicitly given settings on top of `self`, returning the merged configuration
+    ///
+    /// `overrides` has higher precedence (e.g. CLI over environment). Each
+    /// setting is taken from `overrides` whenever it is `Some`, even if it
+    /// equals the default, so `--sort name` wins over `TOOLBOXER_SORT=size`.
+    ///
+    /// # Arguments
+    /// * `overrides` - Settings given on the higher-precedence layer
+    pub fn merge(self, overrides: ConfigOverrides) -> Config {
+        Config {
+            max_depth: overrides.max_depth.or(self.max_depth),
+            sort_by: overrides.sort_by.unwrap_or(self.sort_by),
+            reverse: overrides.reverse.unwrap_or(self.reverse),
+            ..self
+        }
+    }
+
+    /// Formats a byte count the way every size in the output is shown
+    ///
+    /// Uses [`crate::utils::human_bytes`] (powers of 1024) unless `--si` was
+    /// given, so `--du`, the summary views and the size column agree.
+    ///
+    /// # Arguments
+    /// * `bytes` - Byte count to format
+    pub fn format_size(&self, bytes: u64) -> String {
+        if self.si_units {
+            crate::utils::format_size_units(bytes, false)
+        } else {
+            crate::utils::human_bytes(bytes)
         }
     }
 
@@ -80,6 +535,24 @@ impl Config {
         Ok(self)
     }
 
+    /// Restricts the output to a single level of the tree
+    ///
+    /// # Arguments
+    /// * `depth` - Depth to list (0 is the root itself), or `None` for the full tree
+    pub fn with_only_depth(mut self, depth: Option<usize>) -> Self {
+        self.only_depth = depth;
+        self
+    }
+
+    /// Lists the directories whose names match a glob instead of drawing the tree
+    ///
+    /// # Arguments
+    /// * `pattern` - Glob (`*`, `?`) matched against directory names, or `None` for the full tree
+    pub fn with_find_dirs(mut self, pattern: Option<String>) -> Self {
+        self.find_dirs = pattern;
+        self
+    }
+
     /// Sets whether to show hidden files
     ///
     /// # Arguments
@@ -98,6 +571,16 @@ impl Config {
         self
     }
 
+    /// Sets whether the sort order is reversed
+    ///
+    /// # Arguments
+    /// * `reverse` - Whether entries are listed in descending order;
+    ///   ignored with [`SortBy::None`]
+    pub fn with_reverse(mut self, reverse: bool) -> Self {
+        self.reverse = reverse;
+        self
+    }
+
     /// Sets whether to show file permissions
     ///
     /// # Arguments
@@ -116,6 +599,28 @@ impl Config {
         self
     }
 
+    /// Sets whether sizes use decimal SI units
+    ///
+    /// # Arguments
+    /// * `si_units` - `true` for powers of 1000 (kB, MB), `false` for
+    ///   powers of 1024 (KiB, MiB, the default)
+    pub fn with_si_units(mut self, si_units: bool) -> Self {
+        self.si_units = si_units;
+        self
+    }
+
+    /// Sets whether sizes are aligned into a column, like `ls -l`
+    ///
+    /// The column width is the widest size among the displayed files;
+    /// directories leave it blank.
+    ///
+    /// # Arguments
+    /// * `align_sizes` - Whether to right-align sizes before the tree lines
+    pub fn with_align_sizes(mut self, align_sizes: bool) -> Self {
+        self.align_sizes = align_sizes;
+        self
+    }
+
     /// Sets whether to show modification dates
     ///
     /// # Arguments
@@ -125,6 +630,25 @@ impl Config {
         self
     }
 
+    /// Sets whether modification dates are shown relative to now
+    ///
+    /// # Arguments
+    /// * `relative_dates` - Whether dates within the last 30 days read like
+    ///   `3 hours ago`; older dates stay absolute
+    pub fn with_relative_dates(mut self, relative_dates: bool) -> Self {
+        self.relative_dates = relative_dates;
+        self
+    }
+
+    /// Sets whether JSON output is indented
+    ///
+    /// # Arguments
+    /// * `pretty_json` - Whether `Json` output is pretty-printed for reading
+    pub fn with_pretty_json(mut self, pretty_json: bool) -> Self {
+        self.pretty_json = pretty_json;
+        self
+    }
+
     /// Sets a pattern for filtering files
     ///
     /// # 参数
@@ -138,6 +662,351 @@ impl Config {
         self
     }
 
+    /// Sets whether to annotate entries with git status
+    ///
+    /// # Arguments
+    /// * `show_git` - Whether to display `git status --porcelain` markers
+    pub fn with_show_git(mut self, show_git: bool) -> Self {
+        self.show_git = show_git;
+        self
+    }
+
+    /// Sets the maximum number of entries to print
+    ///
+    /// # Arguments
+    /// * `max_files` - Entry limit, `None` for unlimited
+    pub fn with_max_files(mut self, max_files: Option<usize>) -> Self {
+        self.max_files = max_files;
+        self
+    }
+
+    /// Sets whether to append type indicators to entry names
+    ///
+    /// # Arguments
+    /// * `classify` - Whether to mark directories, executables and symlinks
+    pub fn with_classify(mut self, classify: bool) -> Self {
+        self.classify = classify;
+        self
+    }
+
+    /// Sets how entry labels are rendered
+    ///
+    /// # Arguments
+    /// * `path_display` - Name only, root-relative path or absolute path
+    pub fn with_path_display(mut self, path_display: PathDisplay) -> Self {
+        self.path_display = path_display;
+        self
+    }
+
+    /// Sets whether to print only the entry counts
+    ///
+    /// # Arguments
+    /// * `count_only` - Whether to suppress the tree and print totals
+    pub fn with_count_only(mut self, count_only: bool) -> Self {
+        self.count_only = count_only;
+        self
+    }
+
+    /// Sets whether to print the per-extension report
+    ///
+    /// # Arguments
+    /// * `stats_by_ext` - Whether to suppress the tree and tally files by extension
+    pub fn with_stats_by_ext(mut self, stats_by_ext: bool) -> Self {
+        self.stats_by_ext = stats_by_ext;
+        self
+    }
+
+    /// Sets whether hard links are counted once in size totals
+    ///
+    /// # Arguments
+    /// * `count_links_once` - Whether files sharing a device and inode add
+    ///   their size only once, like `du`; has no effect outside Unix
+    pub fn with_count_links_once(mut self, count_links_once: bool) -> Self {
+        self.count_links_once = count_links_once;
+        self
+    }
+
+    /// Sets the inclusive file size range
+    ///
+    /// Directories are never excluded by size.
+    ///
+    /// # Arguments
+    /// * `min_size` - Minimum file size in bytes
+    /// * `max_size` - Maximum file size in bytes
+    pub fn with_size_range(mut self, min_size: Option<u64>, max_size: Option<u64>) -> Self {
+        self.min_size = min_size;
+        self.max_size = max_size;
+        self
+    }
+
+    /// Skips hashing and size accounting for very large files
+    ///
+    /// Unlike `with_size_range` the files are still listed; `--manifest`
+    /// marks them as skipped and size totals leave them out.
+    ///
+    /// # Arguments
+    /// * `limit` - Largest size in bytes that is still processed, or `None` for no limit
+    pub fn with_skip_larger_than(mut self, limit: Option<u64>) -> Self {
+        self.skip_larger_than = limit;
+        self
+    }
+
+    /// Sets the inclusive modification time range
+    ///
+    /// Directories are never excluded by modification time.
+    ///
+    /// # Arguments
+    /// * `newer_than` - Earliest accepted modification time
+    /// * `older_than` - Latest accepted modification time
+    pub fn with_time_range(
+        mut self,
+        newer_than: Option<SystemTime>,
+        older_than: Option<SystemTime>,
+    ) -> Self {
+        self.newer_than = newer_than;
+        self.older_than = older_than;
+        self
+    }
+
+    /// Restricts files to the given extensions
+    ///
+    /// Extensions are matched case-insensitively and may be given with or
+    /// without a leading dot. Directories are always kept.
+    ///
+    /// # Arguments
+    /// * `extensions` - Extensions such as `rs`, `.toml` or `MD`
+    pub fn with_extensions<I, S>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.extensions = extensions
+            .into_iter()
+            .map(|ext| ext.as_ref().trim().trim_start_matches('.').to_lowercase())
+            .filter(|ext| !ext.is_empty())
+            .collect();
+        self
+    }
+
+    /// Sets whether directories are grouped before files
+    ///
+    /// Applied after the chosen sort as a stable partition, so entries keep
+    /// their relative order within the directory and file groups.
+    ///
+    /// # Arguments
+    /// * `dirs_first` - Whether to list directories first
+    pub fn with_dirs_first(mut self, dirs_first: bool) -> Self {
+        self.dirs_first = dirs_first;
+        self
+    }
+
+    /// Sets whether the tree view is preceded by the resolved root path
+    ///
+    /// Only the default tree format prints the header, so machine-readable
+    /// formats stay clean.
+    ///
+    /// # Arguments
+    /// * `print_root` - Whether to print the canonicalized absolute root first
+    pub fn with_print_root(mut self, print_root: bool) -> Self {
+        self.print_root = print_root;
+        self
+    }
+
+    /// Configures the built-in exclude list
+    ///
+    /// # Arguments
+    /// * `enabled` - Whether [`DEFAULT_EXCLUDES`] are pruned
+    /// * `include` - Names removed from the exclude list (e.g. `target`)
+    pub fn with_default_excludes<S: AsRef<str>>(mut self, enabled: bool, include: &[S]) -> Self {
+        if !enabled {
+            self.excludes.clear();
+        }
+        for name in include {
+            self.excludes.remove(name.as_ref());
+        }
+        self
+    }
+
+    /// Adds exclude patterns read from a file, like `rsync --exclude-from`
+    ///
+    /// The file holds one glob pattern per line; blank lines and lines
+    /// starting with `#` are skipped.
+    ///
+    /// # Arguments
+    /// * `path` - Pattern file, or `None` to leave the patterns unchanged
+    ///
+    /// # Returns
+    /// * `Err(Error::Io)` - If the file cannot be read
+    pub fn with_exclude_from(mut self, path: Option<&Path>) -> Result<Self> {
+        if let Some(path) = path {
+            let contents = fs::read_to_string(path)?;
+            self.exclude_patterns.extend(
+                contents
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(str::to_string),
+            );
+        }
+        Ok(self)
+    }
+
+    /// Sets the output format
+    ///
+    /// # Arguments
+    /// * `format` - Text tree or a structured format
+    pub fn with_format(mut self, format: OutputFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Replaces `OutputFormat::Auto` with the concrete format for the output
+    ///
+    /// Like `ls`, a terminal gets the tree and a pipe gets one path per line;
+    /// `--null` also selects the path list since it only applies there (with a
+    /// warning if tree display options were given). Options that only decorate the tree (sizes, dates, child counts, ...)
+    /// keep the tree when piped instead of being silently dropped.
+    /// Explicit formats are left unchanged.
+    ///
+    /// # Arguments
+    /// * `is_terminal` - Whether stdout is a terminal
+    pub fn with_resolved_format(mut self, is_terminal: bool) -> Self {
+        if self.format == OutputFormat::Auto {
+            self.format = if self.null_separated {
+                if self.has_tree_decorations() {
+                    logging::warn("--null lists paths only, tree display options are ignored");
+                }
+                OutputFormat::Paths
+            } else if is_terminal || self.has_tree_decorations() {
+                OutputFormat::Tree
+            } else {
+                OutputFormat::Paths
+            };
+        }
+        self
+    }
+
+    /// Whether any option that only affects the tree view is enabled
+    fn has_tree_decorations(&self) -> bool {
+        self.show_permissions
+            || self.show_size
+            || self.show_date
+            || self.show_git
+            || self.classify
+            || self.child_count
+            || self.dir_totals
+            || self.depth_colors
+            || self.age_colors
+            || self.max_name_width.is_some()
+    }
+
+    /// Sets whether entries are tinted by nesting level
+    ///
+    /// # Arguments
+    /// * `depth_colors` - Whether to cycle entry colors by depth
+    pub fn with_depth_colors(mut self, depth_colors: bool) -> Self {
+        self.depth_colors = depth_colors;
+        self
+    }
+
+    /// Sets whether file names are colored by modification age
+    ///
+    /// Takes precedence over depth colors for files; directories are not
+    /// colored by age.
+    ///
+    /// # Arguments
+    /// * `age_colors` - Whether to color files green/yellow/red by age
+    pub fn with_age_colors(mut self, age_colors: bool) -> Self {
+        self.age_colors = age_colors;
+        self
+    }
+
+    /// Sets whether directories end with a cumulative size line
+    ///
+    /// # Arguments
+    /// * `dir_totals` - Whether to print `∑ <size>` after a directory's children
+    pub fn with_dir_totals(mut self, dir_totals: bool) -> Self {
+        self.dir_totals = dir_totals;
+        self
+    }
+
+    /// Sets the maximum displayed width of entry names
+    ///
+    /// Longer names are shortened with a middle ellipsis that keeps the
+    /// extension visible. Filtering still matches the full name.
+    ///
+    /// # Arguments
+    /// * `max_name_width` - Width in characters, `None` for no truncation
+    pub fn with_max_name_width(mut self, max_name_width: Option<usize>) -> Self {
+        self.max_name_width = max_name_width;
+        self
+    }
+
+    /// Sets whether directories show their number of direct children
+    ///
+    /// Only entries that pass the active filters are counted.
+    ///
+    /// # Arguments
+    /// * `child_count` - Whether to append `(N)` after directory names
+    pub fn with_child_count(mut self, child_count: bool) -> Self {
+        self.child_count = child_count;
+        self
+    }
+
+    /// Sets the base directory that displayed paths are relative to
+    ///
+    /// Overrides `path_display`; paths that cannot be made relative to the
+    /// base (e.g. on another drive) are shown as absolute paths.
+    ///
+    /// # Arguments
+    /// * `relative_to` - Base directory, or `None` to use `path_display`
+    pub fn with_relative_to(mut self, relative_to: Option<PathBuf>) -> Self {
+        self.relative_to = relative_to;
+        self
+    }
+
+    /// Sets whether a root given as a symlink is followed
+    ///
+    /// By default a symlinked root is followed because it was named
+    /// explicitly, while symlinks below it are not (like `-H` in GNU tools).
+    ///
+    /// # Arguments
+    /// * `no_deref_args` - Whether to show a symlinked root as the link itself
+    pub fn with_no_deref_args(mut self, no_deref_args: bool) -> Self {
+        self.no_deref_args = no_deref_args;
+        self
+    }
+
+    /// Sets whether `Paths` output is NUL-terminated (like `find -print0`)
+    ///
+    /// # Arguments
+    /// * `null_separated` - Whether to end each path with a NUL byte
+    pub fn with_null_separated(mut self, null_separated: bool) -> Self {
+        self.null_separated = null_separated;
+        self
+    }
+
+    /// Sets whether output is written with ANSI colors
+    ///
+    /// The tree renderer reads this flag instead of the process-wide
+    /// `colored` override, so each render decides its own coloring.
+    ///
+    /// # Arguments
+    /// * `color` - Whether to emit color escape sequences
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Sets the output colors
+    ///
+    /// # Arguments
+    /// * `theme` - Theme loaded from the config file, or [`Theme::default`]
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
     pub fn with_pattern(mut self, pattern: Option<String>) -> Result<Self> {
         if let Some(p) = pattern {
             // Here you might want to validate the pattern
@@ -146,3 +1015,177 @@ impl Config {
         Ok(self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn merge_takes_given_values_even_when_default() {
+        let env = Config::new(PathBuf::from("env"))
+            .with_sort_by(SortBy::Size)
+            .with_reverse(true)
+            .with_max_depth(3)
+            .unwrap();
+        let merged = env.merge(ConfigOverrides {
+            max_depth: Some(1),
+            sort_by: Some(SortBy::Name),
+            reverse: Some(false),
+        });
+        assert_eq!(merged.root, PathBuf::from("env"));
+        assert_eq!(merged.max_depth, Some(1));
+        assert_eq!(merged.sort_by, SortBy::Name);
+        assert!(!merged.reverse);
+    }
+
+    #[test]
+    fn merge_keeps_lower_layer_when_higher_is_unset() {
+        let env = Config::new(PathBuf::from("env"))
+            .with_sort_by(SortBy::Date)
+            .with_max_depth(2)
+            .unwrap()
+            .with_show_size(true);
+        let merged = env.merge(ConfigOverrides::default());
+        assert_eq!(merged.max_depth, Some(2));
+        assert_eq!(merged.sort_by, SortBy::Date);
+        assert!(merged.show_size);
+    }
+
+    #[test]
+    fn resolved_format_follows_the_terminal() {
+        let auto = Config::new(PathBuf::new()).with_format(OutputFormat::Auto);
+        assert_eq!(
+            auto.clone().with_resolved_format(true).format,
+            OutputFormat::Tree
+        );
+        assert_eq!(
+            auto.clone().with_resolved_format(false).format,
+            OutputFormat::Paths
+        );
+        assert_eq!(
+            auto.with_null_separated(true)
+                .with_resolved_format(true)
+                .format,
+            OutputFormat::Paths
+        );
+
+        let xml = Config::new(PathBuf::new()).with_format(OutputFormat::Xml);
+        assert_eq!(xml.with_resolved_format(false).format, OutputFormat::Xml);
+    }
+
+    #[test]
+    fn auto_is_the_default_and_keeps_the_tree_for_decorations() {
+        let config = Config::new(PathBuf::new());
+        assert_eq!(config.format, OutputFormat::Auto);
+
+        let sized = config.clone().with_show_size(true);
+        assert_eq!(
+            sized.clone().with_resolved_format(false).format,
+            OutputFormat::Tree
+        );
+        assert_eq!(
+            sized
+                .with_null_separated(true)
+                .with_resolved_format(false)
+                .format,
+            OutputFormat::Paths
+        );
+        assert_eq!(
+            config
+                .with_child_count(true)
+                .with_resolved_format(false)
+                .format,
+            OutputFormat::Tree
+        );
+    }
+
+    #[test]
+    fn default_stripe_is_ansi256_gray() {
+        assert_eq!(
+            Theme::default().stripe.0.map(crate::utils::termcolor_color),
+            Some(termcolor::Color::Ansi256(236))
+        );
+    }
+
+    #[test]
+    fn portown_config_only_fills_unset_flags() {
+        let config = PortownConfig {
+            tcp_only: true,
+            listen: true,
+            ..PortownConfig::default()
+        };
+
+        let mut args = PortownArgs::try_parse_from(["portown", "--udp", "-e"]).unwrap();
+        config.apply(&mut args);
+        assert!(args.udp && !args.tcp);
+        assert!(args.established_only && !args.listen);
+
+        let mut args = PortownArgs::try_parse_from(["portown"]).unwrap();
+        config.apply(&mut args);
+        assert!(args.tcp && args.listen);
+    }
+
+    #[test]
+    fn file_config_parses_sections_and_theme() {
+        let config = FileConfig::parse(
+            r#"
+            [tree]
+            default_path = "/srv/app"
+
+            [portown]
+            tcp_only = true
+            listen = true
+
+            [theme]
+            directory = "bright cyan"
+            stripe = "none"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.tree.default_path, Some(PathBuf::from("/srv/app")));
+        assert!(config.portown.tcp_only && config.portown.listen);
+        assert_eq!(
+            config.theme.directory,
+            ThemeColor(Some(colored::Color::BrightCyan))
+        );
+        assert_eq!(config.theme.stripe, ThemeColor(None));
+        assert_eq!(config.theme.size, Theme::default().size);
+    }
+
+    #[test]
+    fn file_config_rejects_unknown_colors_and_keys() {
+        assert!(FileConfig::parse("[theme]\ndirectory = \"octarine\"").is_err());
+        assert!(FileConfig::parse("[tree]\nunknown = 1").is_err());
+    }
+
+    #[test]
+    fn exclude_from_skips_comments_and_blank_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("excludes");
+        fs::write(&path, "# build output\n*.o\n\n  dist/  \n").unwrap();
+
+        let config = Config::new(PathBuf::new())
+            .with_exclude_from(Some(&path))
+            .unwrap();
+        assert_eq!(config.exclude_patterns, vec!["*.o", "dist/"]);
+        assert!(Config::new(PathBuf::new())
+            .with_exclude_from(Some(&dir.path().join("missing")))
+            .is_err());
+    }
+
+    #[test]
+    fn tree_config_default_path_only_applies_without_path() {
+        let config = TreeConfig {
+            default_path: Some(PathBuf::from("/srv/app")),
+        };
+
+        let mut args = TreeArgs::try_parse_from(["tree"]).unwrap();
+        config.apply(&mut args);
+        assert_eq!(args.root(), Path::new("/srv/app"));
+
+        let mut args = TreeArgs::try_parse_from(["tree", "."]).unwrap();
+        config.apply(&mut args);
+        assert_eq!(args.root(), Path::new("."));
+    }
+}
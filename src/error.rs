@@ -24,6 +24,20 @@ pub enum Error {
     #[error("Failed to access path: {}", .0.display())]
     PathAccess(PathBuf),
 
+    /// 表示遍历目录时的错误，保留出错条目的路径和深度
+    #[error(
+        "Failed to traverse {}: {message}",
+        path.as_ref().map_or_else(|| "<unknown>".to_string(), |p| p.display().to_string())
+    )]
+    Traversal {
+        /// 出错条目的路径（walkdir无法确定时为None）
+        path: Option<PathBuf>,
+        /// 出错条目相对于遍历起点的深度
+        depth: usize,
+        /// 底层错误信息
+        message: String,
+    },
+
     /// 表示提供无效深度值时的错误
     #[error("Invalid depth value: {0}")]
     InvalidDepth(i32),
@@ -32,7 +46,90 @@ pub enum Error {
     #[error("Pattern error: {0}")]
     Pattern(String),
 
+    /// 表示命令行参数之间相互冲突
+    #[error("Conflicting arguments: {0}")]
+    ConflictingArgs(String),
+
+    /// 表示配置文件读取或解析错误
+    #[error("Config error: {0}")]
+    Config(String),
+
+    /// 表示SQLite导出时的数据库错误
+    #[cfg(feature = "sqlite")]
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+
     /// 表示其他未指定错误
     #[error("Unknown error: {0}")]
     Other(String),
 }
+
+impl Error {
+    /// 返回错误类型名称，用于机器可读的错误输出
+    pub fn kind_str(&self) -> &'static str {
+        match self {
+            Error::Io(_) => "Io",
+            Error::IntConversion(_) => "IntConversion",
+            Error::PathAccess(_) => "PathAccess",
+            Error::Traversal { .. } => "Traversal",
+            Error::InvalidDepth(_) => "InvalidDepth",
+            Error::Pattern(_) => "Pattern",
+            Error::ConflictingArgs(_) => "ConflictingArgs",
+            Error::Config(_) => "Config",
+            #[cfg(feature = "sqlite")]
+            Error::Sqlite(_) => "Sqlite",
+            Error::Other(_) => "Other",
+        }
+    }
+
+    /// 返回进程退出码
+    ///
+    /// 参数类错误返回2（与clap的用法错误一致），其余返回1
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::InvalidDepth(_) | Error::Pattern(_) | Error::ConflictingArgs(_) => 2,
+            _ => 1,
+        }
+    }
+
+    /// 将错误序列化为JSON：`{"error":{"kind":"Io","message":"..."}}`
+    pub fn to_json(&self) -> String {
+        serde_json::json!({
+            "error": {
+                "kind": self.kind_str(),
+                "message": self.to_string(),
+            }
+        })
+        .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usage_errors_exit_with_two() {
+        assert_eq!(Error::InvalidDepth(-1).exit_code(), 2);
+        assert_eq!(Error::ConflictingArgs("x".into()).exit_code(), 2);
+        assert_eq!(Error::PathAccess(PathBuf::from("x")).exit_code(), 1);
+    }
+
+    #[test]
+    fn to_json_reports_kind_and_message() {
+        let json: serde_json::Value =
+            serde_json::from_str(&Error::Pattern("bad [".into()).to_json()).unwrap();
+        assert_eq!(json["error"]["kind"], "Pattern");
+        assert_eq!(json["error"]["message"], "Pattern error: bad [");
+
+        let traversal = Error::Traversal {
+            path: None,
+            depth: 1,
+            message: "denied".into(),
+        };
+        assert_eq!(
+            traversal.to_string(),
+            "Failed to traverse <unknown>: denied"
+        );
+    }
+}
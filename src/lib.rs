@@ -12,6 +12,8 @@ pub mod commands;
 pub mod config;
 /// 错误类型和结果定义
 pub mod error;
+/// 受`--quiet`/`--verbose`控制的诊断输出
+pub mod logging;
 /// 跨应用程序使用的工具函数
 pub mod utils;
 
@@ -0,0 +1,105 @@
+//! 诊断输出模块
+//!
+//! 提供一个简单的全局日志级别，由`--quiet`和`--verbose`参数设置，
+//! 各命令通过本模块向stderr输出警告和诊断信息，而不是直接调用`eprintln!`。
+//! `log`宏输出的内部诊断日志默认使用同一级别过滤（见[`Level::log_filter`]），
+//! 设置`RUST_LOG`时以其为准。
+
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// 诊断输出级别，级别越高输出越多
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// 仅输出核心数据，不输出任何警告或统计
+    Quiet = 0,
+    /// 默认级别，输出警告
+    Normal = 1,
+    /// `-v`：额外输出过滤统计
+    Verbose = 2,
+    /// `-vv`：额外输出每个被过滤条目的原因
+    Debug = 3,
+}
+
+impl Level {
+    /// 根据命令行参数计算日志级别
+    ///
+    /// # 参数
+    /// * `quiet` - 是否指定了`--quiet`
+    /// * `verbose` - `--verbose`出现的次数
+    pub fn from_flags(quiet: bool, verbose: u8) -> Self {
+        match (quiet, verbose) {
+            (true, _) => Level::Quiet,
+            (false, 0) => Level::Normal,
+            (false, 1) => Level::Verbose,
+            (false, _) => Level::Debug,
+        }
+    }
+
+    /// 对应的`log`日志过滤级别
+    ///
+    /// 默认只输出错误，`-v`增加警告（如无法打开的进程），`-vv`增加调试信息（如执行耗时），
+    /// `--quiet`关闭所有日志。
+    pub fn log_filter(self) -> log::LevelFilter {
+        match self {
+            Level::Quiet => log::LevelFilter::Off,
+            Level::Normal => log::LevelFilter::Error,
+            Level::Verbose => log::LevelFilter::Warn,
+            Level::Debug => log::LevelFilter::Debug,
+        }
+    }
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(Level::Normal as u8);
+
+/// 设置全局日志级别
+pub fn set_level(level: Level) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// 判断给定级别的输出是否启用
+pub fn enabled(level: Level) -> bool {
+    LEVEL.load(Ordering::Relaxed) >= level as u8
+}
+
+/// 输出警告（`--quiet`时不输出）
+pub fn warn(message: impl Display) {
+    if enabled(Level::Normal) {
+        eprintln!("{}", message);
+    }
+}
+
+/// 输出统计信息（需要`-v`）
+pub fn info(message: impl Display) {
+    if enabled(Level::Verbose) {
+        eprintln!("{}", message);
+    }
+}
+
+/// 输出逐条诊断信息（需要`-vv`）
+pub fn debug(message: impl Display) {
+    if enabled(Level::Debug) {
+        eprintln!("{}", message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_filter_follows_the_level() {
+        assert_eq!(Level::Quiet.log_filter(), log::LevelFilter::Off);
+        assert_eq!(Level::Normal.log_filter(), log::LevelFilter::Error);
+        assert_eq!(Level::Verbose.log_filter(), log::LevelFilter::Warn);
+        assert_eq!(Level::Debug.log_filter(), log::LevelFilter::Debug);
+    }
+
+    #[test]
+    fn from_flags_maps_quiet_and_verbose_count() {
+        assert_eq!(Level::from_flags(false, 0), Level::Normal);
+        assert_eq!(Level::from_flags(false, 1), Level::Verbose);
+        assert_eq!(Level::from_flags(false, 5), Level::Debug);
+        assert_eq!(Level::from_flags(true, 2), Level::Quiet);
+    }
+}
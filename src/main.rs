@@ -5,51 +5,136 @@
 //! - 命令路由分发
 //! - 整体错误处理
 
-use clap::Parser;
-use toolboxer::cli::{Cli, Commands};
+use clap::{CommandFactory, Parser};
+use toolboxer::cli::{Cli, ColorWhen, Commands, ErrorFormat, PortownFormat, TreeArgs};
 use toolboxer::commands;
-use toolboxer::config::{Config, SortBy};
+use toolboxer::config::{self, Config, FileConfig, OutputFormat, PathDisplay};
+use toolboxer::logging::{self, Level};
 
 /// Toolboxer应用程序主入口
 ///
 /// # 错误处理
-/// 返回`toolboxer::Result<()>`封装可能出现的各类错误
-fn main() -> toolboxer::Result<()> {
+/// 捕获执行过程中的错误，按`--error-format`输出到stderr并以对应退出码退出
+fn main() {
     // 使用clap解析命令行参数
     let cli = Cli::parse();
+    // 警告、统计和内部诊断日志（如无法打开的进程）都写入stderr，统一由-q/-v控制
+    let level = Level::from_flags(cli.quiet, cli.verbose);
+    logging::set_level(level);
+    // RUST_LOG优先，未设置时使用-q/-v对应的级别
+    env_logger::Builder::from_env(
+        env_logger::Env::default().default_filter_or(level.log_filter().to_string()),
+    )
+    .init();
+    // 未显式指定--color时，TOOLBOXER_NO_COLOR可关闭颜色
+    let color = match cli.color {
+        ColorWhen::Auto if config::no_color_from_env() => false,
+        when => when.enabled(),
+    };
+    colored::control::set_override(color);
 
+    if let Err(err) = run(&cli, color) {
+        match cli.error_format {
+            ErrorFormat::Human => eprintln!("Error: {}", err),
+            ErrorFormat::Json => eprintln!("{}", err.to_json()),
+        }
+        std::process::exit(err.exit_code());
+    }
+}
+
+/// 根据解析后的命令行参数执行对应子命令
+///
+/// `color`为最终的颜色开关，tree通过`Config`使用它
+fn run(cli: &Cli, color: bool) -> toolboxer::Result<()> {
     // 匹配子命令并路由处理逻辑
     match &cli.command {
         // 处理'tree'目录树子命令
         Commands::Tree(args) => {
-            // 创建配置实例并指定根路径
-            let mut config = Config::new(args.path.clone());
+            // 配置文件中的默认路径和颜色主题（文件不存在时使用内置默认值）
+            let file_config = FileConfig::load()?;
+            let mut args = TreeArgs::clone(args);
+            file_config.tree.apply(&mut args);
 
-            // 根据命令行参数配置显示选项
-            // Set maximum traversal depth if specified
-            if let Some(depth) = args.max_depth {
-                config = config.with_max_depth(depth)?;
-            }
+            // 以环境变量提供的默认值创建配置实例并指定根路径
+            let mut config =
+                Config::from_env(args.root().to_path_buf()).with_theme(file_config.theme);
 
+            // 根据命令行参数配置显示选项
             // Configure display options: hidden files, permissions, sizes, and dates
             config = config
+                .with_color(color)
                 .with_show_hidden(args.all)
                 .with_show_permissions(args.permissions)
                 .with_show_size(args.size)
+                .with_si_units(args.si)
+                .with_align_sizes(args.align_sizes)
                 .with_show_date(args.modified)
-                .with_directories_only(args.directories_only);
+                .with_relative_dates(args.relative_dates)
+                .with_only_depth(args.only_depth)
+                .with_find_dirs(args.find_dirs.clone())
+                .with_directories_only(args.directories_only)
+                .with_show_git(args.git)
+                .with_max_files(args.max_files)
+                .with_classify(args.classify)
+                .with_path_display(if args.absolute {
+                    PathDisplay::Absolute
+                } else if args.full_path {
+                    PathDisplay::Relative
+                } else {
+                    PathDisplay::Name
+                })
+                .with_count_only(args.count_only)
+                .with_stats_by_ext(args.stats_by_ext)
+                .with_count_links_once(args.count_links_once)
+                .with_size_range(args.min_size, args.max_size)
+                .with_skip_larger_than(args.skip_larger_than)
+                .with_time_range(args.newer_than, args.older_than)
+                .with_extensions(&args.ext)
+                .with_default_excludes(!args.no_default_excludes, &args.include)
+                .with_exclude_from(args.exclude_from.as_deref())?
+                .with_format(if args.porcelain {
+                    OutputFormat::Porcelain
+                } else if args.manifest {
+                    OutputFormat::Manifest
+                } else if args.summary_tree {
+                    OutputFormat::SummaryTree
+                } else {
+                    args.format
+                })
+                .with_depth_colors(args.depth_colors)
+                .with_age_colors(args.age_colors)
+                .with_dir_totals(args.dir_totals)
+                .with_child_count(args.child_count)
+                .with_max_name_width(args.max_name_width)
+                .with_relative_to(args.relative_to.clone())
+                .with_no_deref_args(args.no_deref_args);
 
-            // 根据命令行标志设置排序模式
-            // Priority: type > size > date > name (default)
-            config = config.with_sort_by(if args.type_sort {
-                SortBy::Type
-            } else if args.size_sort {
-                SortBy::Size
-            } else if args.date_sort {
-                SortBy::Date
-            } else {
-                SortBy::Name
-            });
+            // 命令行显式指定的深度和排序覆盖环境变量，多个排序参数冲突时报错
+            config = config
+                .merge(args.overrides()?)
+                .with_dirs_first(args.dirs_first)
+                .with_print_root(args.print_root)
+                .with_null_separated(args.null);
+
+            config = config.with_pretty_json(args.pretty);
+            if args.pretty && config.format != OutputFormat::Json {
+                Cli::command()
+                    .error(
+                        clap::error::ErrorKind::ArgumentConflict,
+                        "--pretty can only be used with --format json",
+                    )
+                    .exit();
+            }
+
+            // -0只对逐行输出路径的格式有意义（auto时选择路径列表）
+            if args.null && !matches!(config.format, OutputFormat::Paths | OutputFormat::Auto) {
+                Cli::command()
+                    .error(
+                        clap::error::ErrorKind::ArgumentConflict,
+                        "--null can only be used with --format paths",
+                    )
+                    .exit();
+            }
 
             // 应用用户提供的文件名过滤模式
             if let Some(pattern) = &args.filter {
@@ -57,11 +142,29 @@ fn main() -> toolboxer::Result<()> {
             }
 
             // 使用配置参数执行tree命令
-            commands::execute_tree(args, &config)?;
+            commands::execute_tree(&args, &config)?;
         }
         // 处理'portown'端口占用查询命令
         Commands::Portown(args) => {
-            commands::execute_portown(args)?;
+            // 读取配置文件中的默认值（文件不存在时使用内置默认值），命令行参数优先
+            let file_config = FileConfig::load()?;
+            let mut args = args.clone();
+            file_config.portown.apply(&mut args);
+            if args.pretty && args.format != PortownFormat::Json {
+                Cli::command()
+                    .error(
+                        clap::error::ErrorKind::ArgumentConflict,
+                        "--pretty can only be used with --format json",
+                    )
+                    .exit();
+            }
+            commands::execute_portown(&args, &file_config.theme)?;
+        }
+        // 处理'doctor'环境诊断命令，存在失败项时以非零状态退出
+        Commands::Doctor => {
+            if commands::execute_doctor()? > 0 {
+                std::process::exit(1);
+            }
         } // Additional subcommands will be handled here as the toolkit expands
     }
 
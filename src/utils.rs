@@ -3,8 +3,13 @@
 //! 本模块提供常用功能的辅助函数，包括：
 //! 权限格式化、文件元数据处理和输出着色等功能。
 
-use colored::Colorize;
+use crate::error::{Error, Result};
+use colored::{Color, Colorize};
+use humansize::{format_size, BINARY, DECIMAL};
+use sha2::{Digest, Sha256};
+use std::ffi::OsStr;
 use std::fs::{self, Metadata};
+use std::io;
 use std::path::Path;
 
 /// 将文件权限格式化为字符串（例如："rwxr--r--"）
@@ -56,6 +61,194 @@ pub fn format_permissions(metadata: &Metadata) -> String {
     result
 }
 
+/// 将字节数格式化为人类可读的二进制单位字符串（例如："1.5 KiB"）
+///
+/// 所有命令统一使用该函数显示大小，保证输出格式一致。
+///
+/// # 参数
+/// * `n` - 字节数
+///
+/// # 返回值
+/// 带单位的大小字符串
+pub fn human_bytes(n: u64) -> String {
+    format_size(n, BINARY)
+}
+
+/// 按指定的单位制格式化字节数
+///
+/// # 参数
+/// * `n` - 字节数
+/// * `binary` - 为`true`时使用1024进制（KiB、MiB），否则使用1000进制的SI单位（kB、MB）
+///
+/// # 返回值
+/// 带单位的大小字符串，例如1000字节为`1000 B`（二进制）或`1 kB`（SI）
+pub fn format_size_units(n: u64, binary: bool) -> String {
+    if binary {
+        human_bytes(n)
+    } else {
+        format_size(n, DECIMAL)
+    }
+}
+
+/// 解析人类可读的大小字符串（例如："500"、"1K"、"10M"、"1.5GiB"）
+///
+/// 单位不区分大小写，按1024进制换算，可带`B`或`iB`后缀。
+///
+/// # 参数
+/// * `s` - 大小字符串
+///
+/// # 返回值
+/// * `Ok(u64)` - 字节数
+/// * `Err(Error)` - 数字或单位无效时返回`Error::Other`
+pub fn parse_size(s: &str) -> Result<u64> {
+    let invalid = || Error::Other(format!("Invalid size: {}", s));
+    let trimmed = s.trim();
+
+    let split = trimmed
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(split);
+    let number: f64 = number.parse().map_err(|_| invalid())?;
+
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1 << 10,
+        "M" | "MB" | "MIB" => 1 << 20,
+        "G" | "GB" | "GIB" => 1 << 30,
+        "T" | "TB" | "TIB" => 1 << 40,
+        _ => return Err(invalid()),
+    };
+
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// 解析时间范围参数，返回对应的时间点
+///
+/// 支持两种格式：
+/// - 绝对日期`YYYY-MM-DD`（按UTC零点计算）
+/// - 相对时长，如`30s`、`15m`、`24h`、`7d`、`2w`，表示当前时间之前的时长
+///
+/// # 参数
+/// * `s` - 时间字符串
+///
+/// # 返回值
+/// * `Ok(SystemTime)` - 解析得到的时间点
+/// * `Err(Error)` - 格式无效时返回`Error::Other`
+pub fn parse_time_spec(s: &str) -> Result<std::time::SystemTime> {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    let invalid = || Error::Other(format!("Invalid time: {}", s));
+    let trimmed = s.trim();
+
+    // 绝对日期：YYYY-MM-DD
+    let date_parts: Vec<&str> = trimmed.split('-').collect();
+    if let [year, month, day] = date_parts[..] {
+        let year: i64 = year.parse().map_err(|_| invalid())?;
+        let month: i64 = month.parse().map_err(|_| invalid())?;
+        let day: i64 = day.parse().map_err(|_| invalid())?;
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            return Err(invalid());
+        }
+
+        let secs = days_from_civil(year, month, day) * 86_400;
+        return Ok(if secs >= 0 {
+            UNIX_EPOCH + Duration::from_secs(secs as u64)
+        } else {
+            UNIX_EPOCH - Duration::from_secs(secs.unsigned_abs())
+        });
+    }
+
+    // 相对时长：数字 + 单位
+    let split = trimmed
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+    let (number, unit) = trimmed.split_at(split);
+    let number: u64 = number.parse().map_err(|_| invalid())?;
+    let unit_secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3_600,
+        "d" => 86_400,
+        "w" => 604_800,
+        _ => return Err(invalid()),
+    };
+
+    SystemTime::now()
+        .checked_sub(Duration::from_secs(number.saturating_mul(unit_secs)))
+        .ok_or_else(invalid)
+}
+
+/// 计算公历日期距1970-01-01的天数
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// 计算`path`相对于`base`的路径，必要时使用`..`回退
+///
+/// 两个参数都应为绝对路径。路径前缀不同（如Windows上的不同盘符）时
+/// 无法表示为相对路径，返回`None`。
+///
+/// # 参数
+/// * `path` - 目标路径
+/// * `base` - 基准目录
+pub fn relative_path(path: &Path, base: &Path) -> Option<std::path::PathBuf> {
+    use std::path::{Component, PathBuf};
+
+    let mut path_components = path.components().peekable();
+    let mut base_components = base.components().peekable();
+
+    // 盘符或UNC前缀不同则无法得到相对路径
+    if let (Some(Component::Prefix(a)), Some(Component::Prefix(b))) =
+        (path_components.peek(), base_components.peek())
+    {
+        if a != b {
+            return None;
+        }
+    }
+
+    // 跳过公共前缀
+    while let (Some(a), Some(b)) = (path_components.peek(), base_components.peek()) {
+        if a != b {
+            break;
+        }
+        path_components.next();
+        base_components.next();
+    }
+
+    let mut relative = PathBuf::new();
+    for component in base_components {
+        match component {
+            Component::Normal(_) => relative.push(".."),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    relative.extend(path_components);
+    Some(relative)
+}
+
+/// 转义XML属性值中的特殊字符（`&`、`<`、`>`、`"`、`'`）
+pub fn xml_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
 /// 将系统时间格式化为字符串
 ///
 /// # 参数
@@ -70,29 +263,265 @@ pub fn format_time(time: std::time::SystemTime) -> String {
         .unwrap_or_else(|_| "Unknown".to_string())
 }
 
+/// 将时间格式化为相对当前时间的描述，如`3 hours ago`
+///
+/// 见[`format_relative_time_from`]
+pub fn format_relative_time(time: std::time::SystemTime) -> String {
+    format_relative_time_from(time, std::time::SystemTime::now())
+}
+
+/// 将时间格式化为相对`now`的描述
+///
+/// 1分钟内（包括因时钟偏差晚于`now`的时间）为`just now`，
+/// 30天内为`N minutes ago`、`N hours ago`或`N days ago`，更早时退回[`format_time`]。
+///
+/// # 参数
+/// * `time` - 需要格式化的时间
+/// * `now` - 作为基准的当前时间
+pub fn format_relative_time_from(
+    time: std::time::SystemTime,
+    now: std::time::SystemTime,
+) -> String {
+    const MINUTE: u64 = 60;
+    const HOUR: u64 = 60 * MINUTE;
+    const DAY: u64 = 24 * HOUR;
+
+    let secs = now.duration_since(time).map_or(0, |age| age.as_secs());
+    let (count, unit) = match secs {
+        s if s < MINUTE => return "just now".to_string(),
+        s if s < HOUR => (s / MINUTE, "minute"),
+        s if s < DAY => (s / HOUR, "hour"),
+        s if s < 30 * DAY => (s / DAY, "day"),
+        _ => return format_time(time),
+    };
+    format!(
+        "{} {}{} ago",
+        count,
+        unit,
+        if count == 1 { "" } else { "s" }
+    )
+}
+
+/// 将时长格式化为简短的可读字符串，只保留最大的两个单位
+///
+/// 例如`2d 3h`、`5h 12m`、`4m 10s`、`12s`
+pub fn format_duration(duration: std::time::Duration) -> String {
+    let secs = duration.as_secs();
+    let (days, hours, minutes, seconds) = (
+        secs / 86_400,
+        secs % 86_400 / 3_600,
+        secs % 3_600 / 60,
+        secs % 60,
+    );
+
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m {}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
 /// 记录命令执行指标（包含耗时和状态）
 ///
+/// 以`debug`级别写入`log`日志，通过`-vv`查看，不影响标准输出。
+///
 /// # 参数
 /// * `command` - 正在执行的命令
 /// * `duration` - 执行耗时（毫秒）
 /// * `status` - 执行状态（成功/失败）
 /// * `output_size` - 可选输出大小（字节数）
-#[allow(unused_variables)]
 pub fn log_command_metrics(
     command: &str,
-    _duration: u128,
-    _status: &str,
-    _output_size: Option<usize>,
+    duration: u128,
+    status: &str,
+    output_size: Option<usize>,
 ) {
-    /*
-    println!(
-        "[CMD_METRICS] command={} duration={}ms status={} output_size={}",
+    log::debug!(
+        "command={} duration={}ms status={} output_size={}",
         command,
         duration,
         status,
         output_size.unwrap_or(0)
     );
-    */
+}
+
+/// 将值以JSON写入输出并换行，tree和portown的JSON格式共用
+///
+/// 默认为单行的紧凑格式，便于管道处理；`pretty`时使用缩进格式，两者解析结果相同。
+pub fn write_json<T: serde::Serialize + ?Sized>(
+    out: &mut dyn io::Write,
+    value: &T,
+    pretty: bool,
+) -> io::Result<()> {
+    if pretty {
+        serde_json::to_writer_pretty(&mut *out, value)?;
+    } else {
+        serde_json::to_writer(&mut *out, value)?;
+    }
+    writeln!(out)
+}
+
+/// 将文件名或路径转换为可显示的字符串，不合法的UTF-8字节转义为`\xNN`
+///
+/// 与`to_string_lossy`不同，转义保留了原始字节，不同的名称不会都显示为`U+FFFD`。
+pub fn escape_os_str(value: &OsStr) -> String {
+    if let Some(s) = value.to_str() {
+        return s.to_string();
+    }
+
+    let mut escaped = String::new();
+    for chunk in value.as_encoded_bytes().utf8_chunks() {
+        escaped.push_str(chunk.valid());
+        for byte in chunk.invalid() {
+            escaped.push_str(&format!("\\x{:02X}", byte));
+        }
+    }
+    escaped
+}
+
+/// 计算文件内容的SHA-256摘要，返回小写十六进制字符串
+///
+/// 文件内容以流的方式读入，不会一次性加载到内存。
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+/// 判断名称是否匹配通配符模式，`*`匹配任意数量的字节，`?`匹配单个字节
+///
+/// 与[`os_str_contains`]相同按原始字节比较，整个名称都需要匹配。
+pub fn glob_match(pattern: &str, value: &OsStr) -> bool {
+    let pattern = pattern.as_bytes();
+    let value = value.as_encoded_bytes();
+    let (mut p, mut v) = (0, 0);
+    // 最近一个`*`之后的模式位置，以及它当前匹配到的名称位置
+    let mut star: Option<(usize, usize)> = None;
+
+    while v < value.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                star = Some((p + 1, v));
+                p += 1;
+            }
+            Some(&c) if c == b'?' || c == value[v] => {
+                p += 1;
+                v += 1;
+            }
+            _ => match star {
+                // 让`*`多匹配一个字节后重试
+                Some((star_p, star_v)) => {
+                    star = Some((star_p, star_v + 1));
+                    p = star_p;
+                    v = star_v + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// 判断名称是否包含`pattern`，按原始字节匹配，不合法的UTF-8名称同样可以匹配
+pub fn os_str_contains(value: &OsStr, pattern: &str) -> bool {
+    let pattern = pattern.as_bytes();
+    pattern.is_empty()
+        || value
+            .as_encoded_bytes()
+            .windows(pattern.len())
+            .any(|window| window == pattern)
+}
+
+/// 将字符串截断到最多`max`个字符，被截断时以`…`结尾
+///
+/// 按字符而非字节计数，不会切断多字节字符。
+pub fn truncate_ellipsis(value: &str, max: usize) -> String {
+    if value.chars().count() <= max {
+        return value.to_string();
+    }
+    if max == 0 {
+        return String::new();
+    }
+    let mut truncated: String = value.chars().take(max - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// 从中间截断名称到最多`max`个字符，保留开头和扩展名，如`long…name.txt`
+///
+/// 扩展名过长放不下时，开头和结尾各保留约一半。按字符计数。
+pub fn truncate_middle(name: &str, max: usize) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    if chars.len() <= max {
+        return name.to_string();
+    }
+    if max <= 1 {
+        return "…".chars().take(max).collect();
+    }
+
+    let budget = max - 1;
+    let ext_len = name
+        .rfind('.')
+        .filter(|&dot| dot > 0)
+        .map_or(0, |dot| name[dot..].chars().count());
+    let tail_len = if ext_len < budget {
+        ext_len.max(budget / 2)
+    } else {
+        budget / 2
+    };
+    let head_len = budget - tail_len;
+
+    let mut truncated: String = chars[..head_len].iter().collect();
+    truncated.push('…');
+    truncated.extend(&chars[chars.len() - tail_len..]);
+    truncated
+}
+
+/// 获取终端宽度（列数），标准输出不是终端时返回`None`
+pub fn terminal_width() -> Option<usize> {
+    terminal_size::terminal_size().map(|(width, _)| width.0 as usize)
+}
+
+/// 启动分页器进程，标准输入以管道连接
+///
+/// 使用环境变量`PAGER`指定的命令，未设置时默认为`less -R`
+/// （`-R`保留颜色转义序列）。启动失败时返回`None`，由调用方回退到直接输出。
+pub fn spawn_pager() -> Option<std::process::Child> {
+    let pager = std::env::var("PAGER")
+        .ok()
+        .filter(|p| !p.trim().is_empty())
+        .unwrap_or_else(|| "less -R".to_string());
+    let mut parts = pager.split_whitespace();
+    let program = parts.next()?;
+
+    std::process::Command::new(program)
+        .args(parts)
+        .stdin(std::process::Stdio::piped())
+        .spawn()
+        .ok()
+}
+
+/// 返回与全局`--color`设置一致的termcolor颜色选项
+///
+/// 颜色开关由`main`通过`colored::control::set_override`统一设置，
+/// 这里读取同一设置，保证`colored`与`termcolor`的输出一致。
+/// 默认的`--color auto`只在标准输出为终端时启用颜色，
+/// 因此`toolboxer portown > file.txt`得到的是不含转义序列的纯文本。
+pub fn color_choice() -> termcolor::ColorChoice {
+    if colored::control::SHOULD_COLORIZE.should_colorize() {
+        termcolor::ColorChoice::Always
+    } else {
+        termcolor::ColorChoice::Never
+    }
 }
 
 /// 判断文件或目录是否隐藏
@@ -107,18 +536,17 @@ pub fn log_command_metrics(
 /// # 返回值
 /// 如果隐藏返回`true`，否则返回`false`
 pub fn is_hidden(path: &Path) -> bool {
-    if cfg!(windows) {
+    #[cfg(windows)]
+    {
         use std::os::windows::fs::MetadataExt;
-        if let Ok(metadata) = fs::metadata(path) {
+        if let Ok(metadata) = std::fs::metadata(path) {
             // FILE_ATTRIBUTE_HIDDEN = 0x2
             return metadata.file_attributes() & 0x2 != 0;
         }
     }
 
     path.file_name()
-        .and_then(|name| name.to_str())
-        .map(|name| name.starts_with('.'))
-        .unwrap_or(false)
+        .is_some_and(|name| name.as_encoded_bytes().starts_with(b"."))
 }
 
 /// 根据类型为文件/目录名称着色
@@ -132,15 +560,445 @@ pub fn is_hidden(path: &Path) -> bool {
 pub fn colorize_name(name: &str, is_dir: bool) -> String {
     if is_dir {
         name.blue().bold().to_string()
-    } else if name.ends_with(".exe") || name.ends_with(".bat") || name.ends_with(".cmd") {
+    } else if has_executable_extension(name) {
         name.green().to_string()
-    } else if name.ends_with(".rs") || name.ends_with(".toml") {
+    } else if is_source_name(name) {
         name.yellow().to_string()
     } else {
         name.to_string()
     }
 }
 
+/// 判断文件名是否为源代码文件（`.rs`、`.toml`）
+pub fn is_source_name(name: &str) -> bool {
+    name.ends_with(".rs") || name.ends_with(".toml")
+}
+
+/// 按主题颜色为文本着色，颜色为`None`或`enabled`为false时原样返回
+pub fn paint(text: &str, color: crate::config::ThemeColor, enabled: bool) -> String {
+    styled(text, color.0, false, enabled)
+}
+
+/// 以ANSI转义序列为文本设置前景色和粗体
+///
+/// 与`colored`的`Colorize`不同，不读取进程全局的颜色开关，
+/// 是否着色完全由`enabled`决定（tree取自`Config::color`）。
+///
+/// # 参数
+/// * `text` - 需要着色的文本
+/// * `color` - 前景色，为`None`时只应用粗体
+/// * `bold` - 是否加粗
+/// * `enabled` - 是否输出转义序列
+pub fn styled(text: &str, color: Option<Color>, bold: bool, enabled: bool) -> String {
+    let mut codes = Vec::new();
+    if bold {
+        codes.push("1".into());
+    }
+    if let Some(color) = color {
+        codes.push(color.to_fg_str());
+    }
+    if !enabled || codes.is_empty() {
+        return text.to_string();
+    }
+    format!("\x1b[{}m{}\x1b[0m", codes.join(";"), text)
+}
+
+/// 以暗色（ANSI dim）显示文本，`enabled`为false时原样返回
+pub fn dimmed(text: &str, enabled: bool) -> String {
+    if enabled {
+        format!("\x1b[2m{}\x1b[0m", text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// 将`colored`的颜色转换为`termcolor`的颜色
+///
+/// 亮色使用ANSI 256色的8-15号；恰好落在ANSI 256色灰阶（232-255号）上的灰色
+/// 使用对应的灰阶编号，在不支持真彩色的终端上同样可以显示。
+pub fn termcolor_color(color: Color) -> termcolor::Color {
+    use termcolor::Color as Term;
+    match color {
+        Color::Black => Term::Black,
+        Color::Red => Term::Red,
+        Color::Green => Term::Green,
+        Color::Yellow => Term::Yellow,
+        Color::Blue => Term::Blue,
+        Color::Magenta => Term::Magenta,
+        Color::Cyan => Term::Cyan,
+        Color::White => Term::White,
+        Color::BrightBlack => Term::Ansi256(8),
+        Color::BrightRed => Term::Ansi256(9),
+        Color::BrightGreen => Term::Ansi256(10),
+        Color::BrightYellow => Term::Ansi256(11),
+        Color::BrightBlue => Term::Ansi256(12),
+        Color::BrightMagenta => Term::Ansi256(13),
+        Color::BrightCyan => Term::Ansi256(14),
+        Color::BrightWhite => Term::Ansi256(15),
+        // 灰阶第n级为8 + 10n
+        Color::TrueColor { r, g, b } if r == g && g == b && r >= 8 && (r - 8) % 10 == 0 => {
+            Term::Ansi256(232 + (r - 8) / 10)
+        }
+        Color::TrueColor { r, g, b } => Term::Rgb(r, g, b),
+    }
+}
+
+/// ANSI 256色灰阶（232-255号）中指定编号对应的精确灰色
+///
+/// 灰阶第n级（编号232 + n）的RGB值为8 + 10n，[`termcolor_color`]会将其转换回同一编号。
+///
+/// # 参数
+/// * `index` - 灰阶编号，超出232-255时取最接近的一端
+pub fn ansi256_gray(index: u8) -> Color {
+    let value = 8 + 10 * (index.clamp(232, 255) - 232);
+    Color::TrueColor {
+        r: value,
+        g: value,
+        b: value,
+    }
+}
+
+/// `--depth-colors`使用的层级调色板，按深度循环取色
+pub const DEPTH_PALETTE: &[Color] = &[
+    Color::Blue,
+    Color::Green,
+    Color::Yellow,
+    Color::Magenta,
+    Color::Cyan,
+    Color::Red,
+];
+
+/// 返回指定深度在调色板中对应的颜色
+///
+/// # 参数
+/// * `depth` - 条目相对于树根的深度
+pub fn depth_color(depth: usize) -> Color {
+    DEPTH_PALETTE[depth % DEPTH_PALETTE.len()]
+}
+
+/// 根据修改时间距今的长短返回`--age-colors`使用的颜色
+///
+/// 1天内为绿色，30天内为黄色，更早为红色；修改时间晚于当前时间视为最近修改。
+///
+/// # 参数
+/// * `modified` - 文件的修改时间
+pub fn age_color(modified: std::time::SystemTime) -> Color {
+    const DAY_SECS: u64 = 86_400;
+
+    let age = std::time::SystemTime::now()
+        .duration_since(modified)
+        .map(|age| age.as_secs())
+        .unwrap_or(0);
+    if age < DAY_SECS {
+        Color::Green
+    } else if age < 30 * DAY_SECS {
+        Color::Yellow
+    } else {
+        Color::Red
+    }
+}
+
+/// 判断文件是否可执行（目录始终返回`false`）
+///
+/// - 类Unix系统：检查任一执行权限位，没有扩展名的shell脚本同样可以识别
+/// - 其他系统：检查文件名是否带有可执行扩展名（.exe/.bat/.cmd）
+///
+/// # 参数
+/// * `path` - 文件路径，非Unix系统按其文件名判断
+/// * `metadata` - 文件元数据
+pub fn is_executable(path: &Path, metadata: &Metadata) -> bool {
+    if metadata.is_dir() {
+        return false;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = path;
+        metadata.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        path.file_name()
+            .is_some_and(|name| has_executable_extension(&name.to_string_lossy()))
+    }
+}
+
+/// 判断文件名是否带有Windows可执行扩展名（.exe/.bat/.cmd）
+///
+/// # 参数
+/// * `name` - 需要检查的文件名
+pub fn has_executable_extension(name: &str) -> bool {
+    name.ends_with(".exe") || name.ends_with(".bat") || name.ends_with(".cmd")
+}
+
+/// 常用端口的IANA服务名称表：(端口, 协议, 服务名)
+const WELL_KNOWN_SERVICES: &[(u16, &str, &str)] = &[
+    (20, "tcp", "ftp-data"),
+    (21, "tcp", "ftp"),
+    (22, "tcp", "ssh"),
+    (23, "tcp", "telnet"),
+    (25, "tcp", "smtp"),
+    (53, "tcp", "domain"),
+    (53, "udp", "domain"),
+    (67, "udp", "bootps"),
+    (68, "udp", "bootpc"),
+    (69, "udp", "tftp"),
+    (80, "tcp", "http"),
+    (110, "tcp", "pop3"),
+    (123, "udp", "ntp"),
+    (135, "tcp", "epmap"),
+    (135, "udp", "epmap"),
+    (137, "udp", "netbios-ns"),
+    (138, "udp", "netbios-dgm"),
+    (139, "tcp", "netbios-ssn"),
+    (143, "tcp", "imap"),
+    (161, "udp", "snmp"),
+    (389, "tcp", "ldap"),
+    (443, "tcp", "https"),
+    (443, "udp", "https"),
+    (445, "tcp", "microsoft-ds"),
+    (465, "tcp", "submissions"),
+    (514, "udp", "syslog"),
+    (587, "tcp", "submission"),
+    (636, "tcp", "ldaps"),
+    (993, "tcp", "imaps"),
+    (995, "tcp", "pop3s"),
+    (1433, "tcp", "ms-sql-s"),
+    (1434, "udp", "ms-sql-m"),
+    (1900, "udp", "ssdp"),
+    (3306, "tcp", "mysql"),
+    (3389, "tcp", "ms-wbt-server"),
+    (5353, "udp", "mdns"),
+    (5355, "udp", "llmnr"),
+    (5432, "tcp", "postgresql"),
+    (5900, "tcp", "rfb"),
+    (6379, "tcp", "redis"),
+    (8080, "tcp", "http-alt"),
+    (11211, "tcp", "memcache"),
+    (27017, "tcp", "mongodb"),
+];
+
+/// 查询端口对应的常用服务名称
+///
+/// # 参数
+/// * `port` - 端口号
+/// * `proto` - 协议名称（"tcp"/"udp"，不区分大小写）
+///
+/// # 返回值
+/// 内置表中存在时返回服务名，否则返回`None`
+pub fn service_name(port: u16, proto: &str) -> Option<&'static str> {
+    WELL_KNOWN_SERVICES
+        .iter()
+        .find(|(p, pr, _)| *p == port && pr.eq_ignore_ascii_case(proto))
+        .map(|(_, _, name)| *name)
+}
+
 pub fn is_directory(path: &Path) -> std::io::Result<bool> {
     path.metadata().map(|md| md.is_dir())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn ansi256_gray_round_trips_through_termcolor() {
+        use termcolor::Color as Term;
+        for index in 232..=255 {
+            assert_eq!(termcolor_color(ansi256_gray(index)), Term::Ansi256(index));
+        }
+        assert_eq!(
+            ansi256_gray(236),
+            Color::TrueColor {
+                r: 48,
+                g: 48,
+                b: 48
+            }
+        );
+        assert_eq!(ansi256_gray(0), ansi256_gray(232));
+    }
+
+    #[test]
+    fn termcolor_color_maps_grays_to_ansi256() {
+        use termcolor::Color as Term;
+        let gray = |v| Color::TrueColor { r: v, g: v, b: v };
+        assert_eq!(termcolor_color(gray(8)), Term::Ansi256(232));
+        assert_eq!(termcolor_color(gray(48)), Term::Ansi256(236));
+        assert_eq!(termcolor_color(gray(238)), Term::Ansi256(255));
+        assert_eq!(termcolor_color(gray(50)), Term::Rgb(50, 50, 50));
+        assert_eq!(
+            termcolor_color(Color::TrueColor { r: 48, g: 48, b: 0 }),
+            Term::Rgb(48, 48, 0)
+        );
+        assert_eq!(termcolor_color(Color::Blue), Term::Blue);
+        assert_eq!(termcolor_color(Color::BrightRed), Term::Ansi256(9));
+    }
+
+    #[test]
+    fn human_bytes_switches_units_at_1024() {
+        assert_eq!(human_bytes(0), "0 B");
+        assert_eq!(human_bytes(1023), "1023 B");
+        assert_eq!(human_bytes(1024), "1 KiB");
+        assert_eq!(human_bytes(1024 * 1024 * 1024), "1 GiB");
+    }
+
+    #[test]
+    fn service_name_ignores_protocol_case() {
+        assert_eq!(service_name(443, "TCP"), Some("https"));
+        assert_eq!(service_name(53, "udp"), Some("domain"));
+        assert_eq!(service_name(22, "udp"), None);
+    }
+
+    #[test]
+    fn parse_size_accepts_units_and_fractions() {
+        assert_eq!(parse_size("500").unwrap(), 500);
+        assert_eq!(parse_size("1K").unwrap(), 1024);
+        assert_eq!(parse_size("10m").unwrap(), 10 << 20);
+        assert_eq!(parse_size("1.5GiB").unwrap(), 3 << 29);
+        assert_eq!(parse_size(" 2 KB ").unwrap(), 2048);
+    }
+
+    #[test]
+    fn parse_size_rejects_invalid_input() {
+        assert!(parse_size("").is_err());
+        assert!(parse_size("10X").is_err());
+        assert!(parse_size("K").is_err());
+    }
+
+    #[test]
+    fn parse_time_spec_parses_dates_as_utc_midnight() {
+        let time = parse_time_spec("2024-01-01").unwrap();
+        assert_eq!(
+            time.duration_since(UNIX_EPOCH).unwrap().as_secs(),
+            1_704_067_200
+        );
+        assert_eq!(parse_time_spec("1970-01-01").unwrap(), UNIX_EPOCH);
+    }
+
+    #[test]
+    fn parse_time_spec_parses_relative_durations() {
+        let before = SystemTime::now();
+        let time = parse_time_spec("2h").unwrap();
+        let age = before.duration_since(time).unwrap_or_default();
+        assert!(age >= Duration::from_secs(7_199) && age <= Duration::from_secs(7_260));
+    }
+
+    #[test]
+    fn parse_time_spec_rejects_invalid_input() {
+        assert!(parse_time_spec("2024-13-01").is_err());
+        assert!(parse_time_spec("7y").is_err());
+        assert!(parse_time_spec("yesterday").is_err());
+    }
+
+    #[test]
+    fn relative_path_walks_up_with_dotdot() {
+        assert_eq!(
+            relative_path(Path::new("/a/b/c"), Path::new("/a/d")),
+            Some(std::path::PathBuf::from("../b/c"))
+        );
+        assert_eq!(
+            relative_path(Path::new("/a"), Path::new("/a")),
+            Some(std::path::PathBuf::new())
+        );
+    }
+
+    #[test]
+    fn escape_os_str_escapes_invalid_utf8() {
+        assert_eq!(escape_os_str(OsStr::new("plain")), "plain");
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            assert_eq!(escape_os_str(OsStr::from_bytes(b"a\xffb")), "a\\xFFb");
+        }
+    }
+
+    #[test]
+    fn truncate_middle_keeps_extension() {
+        assert_eq!(truncate_middle("short.txt", 20), "short.txt");
+        assert_eq!(truncate_middle("a_very_long_name.txt", 12), "a_very…e.txt");
+        assert_eq!(
+            truncate_middle("a_very_long_name.txt", 12).chars().count(),
+            12
+        );
+        assert_eq!(truncate_middle("abcdefghij", 5), "ab…ij");
+        assert_eq!(truncate_middle("abcdef", 1), "…");
+        assert_eq!(truncate_middle("abcdef", 0), "");
+    }
+
+    #[test]
+    fn format_size_units_switches_between_binary_and_si() {
+        assert_eq!(format_size_units(1536, true), "1.50 KiB");
+        assert_eq!(format_size_units(1500, false), "1.50 kB");
+    }
+
+    #[test]
+    fn glob_match_handles_wildcards() {
+        assert!(glob_match("*.rs", OsStr::new("main.rs")));
+        assert!(glob_match("build", OsStr::new("build")));
+        assert!(glob_match("b?ild*", OsStr::new("buildx")));
+        assert!(glob_match("*", OsStr::new("")));
+        assert!(glob_match("a*b*c", OsStr::new("aXbYbZc")));
+        assert!(!glob_match("*.rs", OsStr::new("main.rs.bak")));
+        assert!(!glob_match("build", OsStr::new("rebuild")));
+        assert!(!glob_match("?", OsStr::new("")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn is_executable_checks_mode_bits_without_extension() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("deploy");
+        std::fs::write(&script, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(is_executable(&script, &std::fs::metadata(&script).unwrap()));
+
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o644)).unwrap();
+        assert!(!is_executable(
+            &script,
+            &std::fs::metadata(&script).unwrap()
+        ));
+        assert!(!is_executable(
+            dir.path(),
+            &std::fs::metadata(dir.path()).unwrap()
+        ));
+    }
+
+    #[test]
+    fn format_relative_time_from_uses_largest_unit() {
+        let now = UNIX_EPOCH + Duration::from_secs(100 * 86_400);
+        let ago = |secs| format_relative_time_from(now - Duration::from_secs(secs), now);
+        assert_eq!(ago(30), "just now");
+        assert_eq!(ago(60), "1 minute ago");
+        assert_eq!(ago(3 * 3_600 + 59), "3 hours ago");
+        assert_eq!(ago(86_400), "1 day ago");
+        assert_eq!(ago(29 * 86_400), "29 days ago");
+        // 30天及以上退回绝对时间
+        assert_eq!(ago(30 * 86_400), (70 * 86_400).to_string());
+        // 晚于当前时间视为刚刚
+        assert_eq!(
+            format_relative_time_from(now + Duration::from_secs(5), now),
+            "just now"
+        );
+    }
+
+    #[test]
+    fn write_json_is_compact_unless_pretty() {
+        let value = serde_json::json!({"a": [1, 2]});
+        let mut compact = Vec::new();
+        write_json(&mut compact, &value, false).unwrap();
+        assert_eq!(String::from_utf8(compact).unwrap(), "{\"a\":[1,2]}\n");
+
+        let mut pretty = Vec::new();
+        write_json(&mut pretty, &value, true).unwrap();
+        let pretty = String::from_utf8(pretty).unwrap();
+        assert!(pretty.contains("\n  \"a\": [\n"));
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&pretty).unwrap(),
+            value
+        );
+    }
+}